@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use clap::{Parser};
 
+use crate::config::OutputFormat;
+
 use super::CliCommand;
 
 #[derive(Parser)]
@@ -11,10 +13,38 @@ pub struct Cli {
     #[arg(short, long, value_name = "FILE")]
     pub storage: Option<PathBuf>,
 
+    /// Sets a custom configuration file location
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Registry to use instead of default one
+    #[arg(short, long)]
+    pub registry: Option<String>,
+
+    /// Personal registry unlocking team registries as a member, its password is asked instead
+    #[arg(long, value_name = "REGISTRY")]
+    pub identity: Option<String>,
+
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub debug: u8,
 
+    /// Skip hash chain verification when loading registry
+    #[arg(long)]
+    pub no_verify: bool,
+
+    /// Seconds to wait for registry lock held by another process
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub lock_timeout: u64,
+
+    /// Output format of listing commands
+    #[arg(long, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
+
+    /// Seconds to keep copied secret in clipboard, 0 keeps it
+    #[arg(long, value_name = "SECONDS")]
+    pub clipboard_timeout: Option<u64>,
+
     #[command(subcommand)]
     pub command: CliCommand,
 }
\ No newline at end of file
@@ -1,4 +1,8 @@
-use clap::{Subcommand, arg};
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+use super::{ConfigCommand, ConflictPolicy, FileFormat, InitArgs, MemberCommand, MetadataMode, RegistryCommand, SyncPreference, parse_point_in_time};
 
 #[derive(Subcommand)]
 pub enum CliCommand {
@@ -6,14 +10,36 @@ pub enum CliCommand {
     Init {
         /// Registry name
         name: Option<String>,
-        
-        // Registry password
-        #[arg(short, long)]
-        password: Option<String>,
+
+        #[command(flatten)]
+        init: InitArgs,
+    },
+
+    /// Manage registries in storage
+    Registry {
+        #[command(subcommand)]
+        command: RegistryCommand,
+    },
+
+    /// Read and change configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
     },
 
     /// List entries
     Ls {
+        /// List entries of every registry in storage
+        #[arg(short, long)]
+        all: bool,
+
+        /// List entries as they were at given time
+        #[arg(long, value_name = "TIME", value_parser = parse_point_in_time)]
+        at: Option<u128>,
+
+        /// Registry password, required for registries with private metadata
+        #[arg(short, long)]
+        password: Option<String>,
     },
 
     /// Copy entry secret to clipboard
@@ -38,6 +64,24 @@ pub enum CliCommand {
         /// Copy secret to clipboard
         #[arg(short, long)]
         copy: bool,
+
+        /// Display previous entry version listed by history command
+        #[arg(long, value_name = "N", conflicts_with("at"))]
+        version: Option<usize>,
+
+        /// Display entry as it was at given time
+        #[arg(long, value_name = "TIME", value_parser = parse_point_in_time)]
+        at: Option<u128>,
+    },
+
+    /// List every change of entry, including renames
+    History {
+        /// Entry name
+        name: String,
+
+        /// Registry password, required for registries with private metadata
+        #[arg(short, long)]
+        password: Option<String>,
     },
 
     /// Add entry
@@ -53,7 +97,7 @@ pub enum CliCommand {
         #[arg(short, long, required_unless_present("generate"))]
         secret: Option<String>,
 
-        /// Generate entry secret {length:[d][s][l][u]}
+        /// Generate entry secret {length:[d][s][l][u]} or configured generator preset
         /// d - use digits
         /// s - use symbols
         /// l - use lowercase letters
@@ -64,6 +108,10 @@ pub enum CliCommand {
         /// Copy secret to clipboard
         #[arg(short, long)]
         copy: bool,
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
     },
     
 
@@ -84,7 +132,7 @@ pub enum CliCommand {
         #[arg(short, long)]
         secret: Option<String>,
 
-        /// Generate entry secret {length:[n][s][l][L]}
+        /// Generate entry secret {length:[n][s][l][L]} or configured generator preset
         /// n - use numbers
         /// s - use symbols
         /// l - use lowercase letters
@@ -95,12 +143,225 @@ pub enum CliCommand {
         /// Copy secret to clipboard
         #[arg(short, long, requires("secret"), requires("generate"))]
         copy: bool,
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
     },
     
 
+    /// Restore entry or whole registry to its state at given time
+    Revert {
+        /// Entry name
+        #[arg(required_unless_present("all"))]
+        name: Option<String>,
+
+        /// Revert every entry of registry
+        #[arg(short, long, conflicts_with("name"))]
+        all: bool,
+
+        /// Time to restore state of
+        #[arg(short, long, value_name = "TIME", value_parser = parse_point_in_time)]
+        to: u128,
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
     /// Delete entry
     Del {
         /// Entry name
         name: String,
-    }    
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Write decrypted entries in exchange format
+    Export {
+        /// Exchange format
+        #[arg(short, long, value_enum)]
+        format: FileFormat,
+
+        /// Export only entries with names starting with prefix
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// File to write instead of standard output, required for kdbx
+        #[arg(required_if_eq("format", "kdbx"))]
+        file: Option<PathBuf>,
+
+        /// Password of kdbx file
+        #[arg(long)]
+        file_password: Option<String>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Registry password
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Add entries from exchange format file
+    Import {
+        /// File to read
+        file: PathBuf,
+
+        /// Exchange format
+        #[arg(short, long, value_enum)]
+        format: FileFormat,
+
+        /// What to do with entries that already exist
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Skip)]
+        on_conflict: ConflictPolicy,
+
+        /// Only display what would be imported
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Password of kdbx file
+        #[arg(long)]
+        file_password: Option<String>,
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Merge diverged replica of registry and write result to both sides
+    Sync {
+        /// Path to other replica of registry
+        path: PathBuf,
+
+        /// Resolve conflicting entry changes without asking
+        #[arg(long, value_enum)]
+        prefer: Option<SyncPreference>,
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Write verified registry snapshot to backup bundle
+    Backup {
+        /// Directory for bundles, backups directory in storage by default
+        #[arg(long, value_name = "DIR")]
+        dest: Option<PathBuf>,
+
+        /// Remove older bundles of registry keeping N latest
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        keep: Option<u64>,
+    },
+
+    /// Replace registry with content of backup bundle
+    Restore {
+        /// Backup bundle
+        bundle: PathBuf,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Password of registry at the time of backup
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Rewrite registry log keeping only live entries
+    Compact {
+        /// Keep previous registry log as timestamped archive
+        #[arg(short, long)]
+        archive: bool,
+
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Repair registry log after interrupted write
+    Repair {
+        /// Drop incomplete trailing record
+        #[arg(long)]
+        truncate_tail: bool,
+    },
+
+    /// Change registry password
+    Passwd {
+        /// Current registry password
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// New registry password
+        #[arg(short, long)]
+        new_password: Option<String>,
+
+        /// Password key derivation memory in KiB
+        #[arg(long)]
+        kdf_memory: Option<u32>,
+
+        /// Password key derivation iterations
+        #[arg(long)]
+        kdf_iterations: Option<u32>,
+    },
+
+    /// Generate new registry key and re-encrypt every secret
+    RotateKey {
+        /// Registry password
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// Re-encrypt whole history instead of live entries only
+        #[arg(short, long)]
+        keep_history: bool,
+    },
+
+    /// Manage team members of registry
+    Member {
+        #[command(subcommand)]
+        command: MemberCommand,
+    },
+
+    /// Re-encrypt every secret padded with configured padding scheme
+    Pad {
+        /// Registry password
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// Re-encrypt whole history instead of live entries only
+        #[arg(short, long)]
+        keep_history: bool,
+
+        /// Do not ask for confirmation before dropping history
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Upgrade registry file to current format keeping a backup
+    Migrate {
+        /// Registry password, required to sign the upgraded header
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Encrypt or decrypt entry names and descriptions of registry keeping a backup
+    Metadata {
+        /// Metadata mode to convert registry to
+        #[arg(value_enum)]
+        mode: MetadataMode,
+
+        /// Registry password
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// Verify registry operations hash chain
+    Verify {
+        /// Registry password, required for authenticated registries
+        #[arg(short, long)]
+        password: Option<String>,
+    },
 }
\ No newline at end of file
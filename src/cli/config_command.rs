@@ -0,0 +1,23 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Display effective value of configuration key
+    Get {
+        /// Configuration key, e.g. storage or generators.strong
+        key: String,
+    },
+
+    /// Store configuration key in configuration file
+    Set {
+        /// Configuration key, e.g. storage or generators.strong
+        key: String,
+
+        /// Configuration value
+        value: String,
+    },
+
+    /// List effective configuration
+    List {
+    },
+}
@@ -0,0 +1,25 @@
+use clap::ValueEnum;
+
+use enigmatic::ImportConflict;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Keep existing entry
+    Skip,
+
+    /// Replace description and secret of existing entry
+    Overwrite,
+
+    /// Add imported entry under free name with numeric suffix
+    Rename,
+}
+
+impl From<ConflictPolicy> for ImportConflict {
+    fn from(policy: ConflictPolicy) -> Self {
+        match policy {
+            ConflictPolicy::Skip => ImportConflict::Skip,
+            ConflictPolicy::Overwrite => ImportConflict::Overwrite,
+            ConflictPolicy::Rename => ImportConflict::Rename,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use clap::ValueEnum;
+
+use enigmatic::ExchangeFormat;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FileFormat {
+    Json,
+    Csv,
+    Dotenv,
+    Kdbx,
+    Bitwarden,
+    #[value(name = "1password")]
+    OnePassword,
+    ChromeCsv,
+    FirefoxCsv,
+}
+
+impl From<FileFormat> for ExchangeFormat {
+    fn from(format: FileFormat) -> Self {
+        match format {
+            FileFormat::Json => ExchangeFormat::Json,
+            FileFormat::Csv => ExchangeFormat::Csv,
+            FileFormat::Dotenv => ExchangeFormat::Dotenv,
+            FileFormat::Kdbx => ExchangeFormat::Kdbx,
+            FileFormat::Bitwarden => ExchangeFormat::Bitwarden,
+            FileFormat::OnePassword => ExchangeFormat::OnePassword,
+            FileFormat::ChromeCsv => ExchangeFormat::ChromeCsv,
+            FileFormat::FirefoxCsv => ExchangeFormat::FirefoxCsv,
+        }
+    }
+}
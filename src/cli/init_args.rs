@@ -0,0 +1,32 @@
+use clap::Args;
+
+use enigmatic::{DEFAULT_KDF_MEMORY, DEFAULT_KDF_ITERATIONS};
+
+use super::StorageBackend;
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Registry password
+    #[arg(short, long)]
+    pub password: Option<String>,
+
+    /// Require every operation to be authenticated with registry key
+    #[arg(short, long)]
+    pub authenticated: bool,
+
+    /// Encrypt entry names and descriptions, listing entries then requires the password
+    #[arg(long)]
+    pub private_metadata: bool,
+
+    /// Password key derivation memory in KiB
+    #[arg(long, default_value_t = DEFAULT_KDF_MEMORY)]
+    pub kdf_memory: u32,
+
+    /// Password key derivation iterations
+    #[arg(long, default_value_t = DEFAULT_KDF_ITERATIONS)]
+    pub kdf_iterations: u32,
+
+    /// Storage backend of the new registry
+    #[arg(long, value_enum, default_value_t)]
+    pub backend: StorageBackend,
+}
@@ -0,0 +1,36 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum MemberCommand {
+    /// Add team member able to read every secret with its own registry key
+    Add {
+        /// Member public key in hex, printed by `registry key` on the member side
+        public_key: String,
+
+        /// Member name
+        #[arg(long)]
+        name: String,
+
+        /// Registry password
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+
+    /// List team members
+    List {
+    },
+
+    /// Remove team member and re-encrypt every secret it could read
+    Remove {
+        /// Member name
+        name: String,
+
+        /// Flag secrets the member could read for rotation
+        #[arg(long)]
+        rotate: bool,
+
+        /// Registry password
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+}
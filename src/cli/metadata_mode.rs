@@ -0,0 +1,10 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MetadataMode {
+    /// Encrypt entry names and descriptions
+    Private,
+
+    /// Store entry names and descriptions in plaintext
+    Public,
+}
@@ -1,5 +1,26 @@
+#[allow(clippy::module_inception)]
 mod cli;
 mod cli_command;
+mod config_command;
+mod conflict_policy;
+mod file_format;
+mod init_args;
+mod member_command;
+mod metadata_mode;
+mod point_in_time;
+mod registry_command;
+mod storage_backend;
+mod sync_preference;
 
 pub use cli::Cli;
-pub use cli_command::CliCommand;
\ No newline at end of file
+pub use cli_command::CliCommand;
+pub use config_command::ConfigCommand;
+pub use conflict_policy::ConflictPolicy;
+pub use file_format::FileFormat;
+pub use init_args::InitArgs;
+pub use member_command::MemberCommand;
+pub use metadata_mode::MetadataMode;
+pub use point_in_time::parse_point_in_time;
+pub use registry_command::RegistryCommand;
+pub use storage_backend::StorageBackend;
+pub use sync_preference::SyncPreference;
\ No newline at end of file
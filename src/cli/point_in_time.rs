@@ -0,0 +1,23 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+/// Parses point in time as RFC 3339, local `YYYY-MM-DD[ HH:MM[:SS]]` or unix milliseconds
+pub fn parse_point_in_time(value: &str) -> Result<u128, String> {
+    if let Ok(millis) = value.parse::<u128>() {
+        return Ok(millis);
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp_millis() as u128);
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0)));
+
+    naive
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|time| time.timestamp_millis() as u128)
+        .ok_or_else(|| format!("Invalid point in time {}", value))
+}
@@ -0,0 +1,41 @@
+use clap::Subcommand;
+
+use super::InitArgs;
+
+#[derive(Subcommand)]
+pub enum RegistryCommand {
+    /// Create new registry in storage
+    Create {
+        /// Registry name
+        name: String,
+
+        #[command(flatten)]
+        init: InitArgs,
+    },
+
+    /// List registries in storage
+    List {
+    },
+
+    /// Remember registry as default
+    Use {
+        /// Registry name
+        name: String,
+    },
+
+    /// Print registry public key to share it as a team member key
+    Key {
+        /// Registry name, the selected registry by default
+        name: Option<String>,
+    },
+
+    /// Remove registry with all its entries
+    Remove {
+        /// Registry name
+        name: String,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
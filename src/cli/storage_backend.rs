@@ -0,0 +1,11 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum StorageBackend {
+    /// Single registry file
+    #[default]
+    File,
+
+    /// SQLite database with one row per operation, for large registries
+    Sqlite,
+}
@@ -0,0 +1,13 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SyncPreference {
+    /// Keep changes made in this registry
+    Local,
+
+    /// Keep changes made in other registry
+    Remote,
+
+    /// Keep side with the latest change of entry
+    Newest,
+}
@@ -0,0 +1,129 @@
+use std::{collections::BTreeMap, env, fs, io, path::{Path, PathBuf}};
+
+use enigmatic::{Error, SecretPadding};
+use serde::{Deserialize, Serialize};
+
+use super::OutputFormat;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    pub storage: Option<PathBuf>,
+    pub registry: Option<String>,
+    pub clipboard_timeout: Option<u64>,
+    pub output: Option<OutputFormat>,
+    pub padding: Option<String>,
+    #[serde(default)]
+    pub generators: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn default_path() -> PathBuf {
+        if let Ok(path) = env::var("ENIGMATIC_CONFIG") {
+            return PathBuf::from(path);
+        }
+
+        let config_home = env::var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| home::home_dir().unwrap_or_default().join(".config"));
+
+        config_home.join("enigmatic").join("config.toml")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|error| Error::InvalidData(
+                format!("Invalid configuration file {}: {}", path.display(), error),
+            )),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self)
+            .map_err(|error| Error::InvalidInput(error.to_string()))?;
+
+        fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    pub fn with_env(mut self) -> Result<Self, Error> {
+        for key in KEYS {
+            if let Ok(value) = env::var(format!("{}{}", ENV_PREFIX, key.to_uppercase())) {
+                self.set(key, &value)?;
+            }
+        }
+
+        for (key, value) in env::vars() {
+            if let Some(name) = key.strip_prefix(GENERATOR_ENV_PREFIX) {
+                self.generators.insert(name.to_lowercase(), value);
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "storage" => self.storage.as_ref().map(|path| path.display().to_string()),
+            "registry" => self.registry.clone(),
+            "clipboard_timeout" => self.clipboard_timeout.map(|timeout| timeout.to_string()),
+            "output" => self.output.map(|output| output.to_string()),
+            "padding" => self.padding.clone(),
+            _ => key.strip_prefix("generators.").and_then(|name| self.generators.get(name).cloned()),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        match key {
+            "storage" => self.storage = Some(PathBuf::from(value)),
+            "registry" => self.registry = Some(String::from(value)),
+            "clipboard_timeout" => self.clipboard_timeout = Some(value.parse().map_err(|_| invalid_value(key, value))?),
+            "output" => self.output = Some(value.parse().map_err(|_| invalid_value(key, value))?),
+            "padding" => self.padding = Some(value.parse::<SecretPadding>()?.to_string()),
+            _ => match key.strip_prefix("generators.") {
+                Some(name) if !name.is_empty() => {
+                    self.generators.insert(String::from(name), String::from(value));
+                },
+                _ => return Err(Error::InvalidInput(format!("Unknown configuration key {}", key))),
+            },
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut result: Vec<_> = KEYS
+            .iter()
+            .filter_map(|key| self.get(key).map(|value| (String::from(*key), value)))
+            .collect();
+
+        for (name, format) in &self.generators {
+            result.push((format!("generators.{}", name), format.clone()));
+        }
+
+        result
+    }
+
+    /// Padding scheme of newly encrypted secrets, powers of two unless configured.
+    pub fn padding(&self) -> Result<SecretPadding, Error> {
+        self.padding.as_deref().map(str::parse).unwrap_or(Ok(SecretPadding::default()))
+    }
+
+    pub fn generator<'a>(&'a self, generate: &'a str) -> &'a str {
+        self.generators.get(generate).map(String::as_str).unwrap_or(generate)
+    }
+}
+
+const ENV_PREFIX: &str = "ENIGMATIC_";
+const GENERATOR_ENV_PREFIX: &str = "ENIGMATIC_GENERATOR_";
+const KEYS: [&str; 5] = ["storage", "registry", "clipboard_timeout", "output", "padding"];
+
+fn invalid_value(key: &str, value: &str) -> Error {
+    Error::InvalidInput(format!("Invalid value {} for configuration key {}", value, key))
+}
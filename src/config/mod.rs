@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod config;
+mod output_format;
+
+pub use config::Config;
+pub use output_format::OutputFormat;
@@ -0,0 +1,7 @@
+use std::path::PathBuf;
+
+pub struct CompactionReport {
+    pub original_size: u64,
+    pub compacted_size: u64,
+    pub archive: Option<PathBuf>,
+}
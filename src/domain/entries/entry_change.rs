@@ -0,0 +1,9 @@
+pub enum EntryChange {
+    Added,
+    Changed {
+        renamed_from: Option<String>,
+        description: bool,
+        secret: bool,
+    },
+    Deleted,
+}
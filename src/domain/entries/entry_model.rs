@@ -1,5 +1,7 @@
+#[derive(Clone)]
 pub struct EntryModel {
     pub timestamp: u128,
+    pub secret_timestamp: u128,
     pub description: String,
     pub secret: Vec<u8>,
 }
\ No newline at end of file
@@ -0,0 +1,10 @@
+use super::EntryChange;
+
+pub struct EntryRevision {
+    pub version: usize,
+    pub timestamp: u128,
+    pub change: EntryChange,
+    pub name: String,
+    pub description: String,
+    pub secret: Vec<u8>,
+}
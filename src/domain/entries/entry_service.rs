@@ -1,201 +1,1174 @@
-use std::{collections::BTreeMap, io, time::{SystemTime, UNIX_EPOCH}};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, path::{Path, PathBuf}, str, time::{SystemTime, UNIX_EPOCH}};
 
 use sha3::{Digest, Sha3_256};
+use zeroize::Zeroizing;
 
-use crate::storage::{registries::RegistryRepository, entries::EntryOperationDto};
+use crate::{errors::Error, storage::{registries::{RegistryRepository, RegistryMember}, entries::EntryOperationDto, exchange::ExchangeRecord, backups::BackupBundle}};
 
-use super::EntryModel;
+use super::{EntryModel, EntryChange, EntryDiff, EntryRevision, CompactionReport, ImportConflict, ImportReport, RotationReport, SyncConflict, SyncReport, SyncSide};
 
+/// Replays a registry log into its current entries and appends new operations to it.
 pub struct EntryService {
     registry_repository: RegistryRepository,
     last_hash: [u8; 64],
-    pub entries: BTreeMap<String, EntryModel>,
+    verify: bool,
+    until: Option<u128>,
+    operations: usize,
+    authenticated: usize,
+    unauthenticated: Vec<(usize, u64)>,
+    entries: BTreeMap<String, EntryModel>,
 }
 
 impl EntryService {
-    pub fn new(registry_repository: RegistryRepository) -> Result<Self, io::Error> {
-        let mut result = Self {
+    /// Replays the whole log of `registry_repository`, checking the hash chain when `verify` is set.
+    /// Registries requiring authentication have every operation checked once unlocked, regardless of `verify`.
+    pub fn new(registry_repository: RegistryRepository, verify: bool) -> Result<Self, Error> {
+        let mut result = Self::empty(registry_repository, verify);
+
+        result.replay()?;
+
+        Ok(result)
+    }
+
+    /// Replays the log up to `until` (milliseconds since the epoch). The result is read only.
+    pub fn at(registry_repository: RegistryRepository, verify: bool, until: u128) -> Result<Self, Error> {
+        let mut result = Self::empty(registry_repository, verify);
+        result.until = Some(until);
+
+        result.replay()?;
+
+        Ok(result)
+    }
+
+    fn empty(registry_repository: RegistryRepository, verify: bool) -> Self {
+        Self {
             registry_repository,
             last_hash: [0u8; 64],
+            verify,
+            until: None,
+            operations: 0,
+            authenticated: 0,
+            unauthenticated: Vec::new(),
             entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_backup(bundle: &BackupBundle, path: &Path, password: &str) -> Result<Self, Error> {
+        let mut registry_repository = RegistryRepository::stage(path, &bundle.header_region)?;
+
+        let log = registry_repository.unlock(password).and_then(|_| {
+            registry_repository.decrypt(&bundle.encrypted_log).ok_or_else(|| Error::InvalidData(
+                String::from("Can not decrypt backup bundle log"),
+            ))
+        });
+
+        let log = match log {
+            Ok(log) => log,
+            Err(error) => {
+                registry_repository.discard()?;
+                return Err(error);
+            },
         };
 
-        while let Some(entry_change) = result.registry_repository.read_operation()? {
-            match entry_change {
-                EntryOperationDto::Add { hash, timestamp, name, description, secret } => {
-                    result.last_hash = hash;
+        registry_repository.write_log(&log)?;
+
+        let mut result = Self::empty(registry_repository, true);
+
+        if let Err(error) = result.replay() {
+            result.discard()?;
+            return Err(error);
+        }
+
+        if result.torn_tail().is_some() 
+            || result.operations as u64 != bundle.operations 
+            || result.entries.len() as u64 != bundle.entries 
+            || result.last_hash != bundle.last_hash {
+            result.discard()?;
+            return Err(Error::InvalidData(String::from("Backup bundle content does not match its metadata")));
+        }
+
+        Ok(result)
+    }
+
+    pub fn registry_name(&self) -> &str {
+        &self.registry_repository.name
+    }
+
+    pub fn operations(&self) -> usize {
+        self.operations
+    }
+
+    pub fn torn_tail(&self) -> Option<u64> {
+        self.registry_repository.torn_tail()
+    }
+
+    pub fn authenticated_operations(&self) -> Option<usize> {
+        if self.registry_repository.unlocked() {
+            Some(self.authenticated)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Indices and offsets of operations written without an authentication tag, known once the registry is unlocked.
+    pub fn unauthenticated_operations(&self) -> &[(usize, u64)] {
+        &self.unauthenticated
+    }
+
+    /// Live entries by name, as of the last replay.
+    pub fn entries(&self) -> &BTreeMap<String, EntryModel> {
+        &self.entries
+    }
+
+    /// Live entry named `name`, as of the last replay.
+    pub fn get(&self, name: &str) -> Option<&EntryModel> {
+        self.entries.get(name)
+    }
+
+    /// Team members of the registry, removed ones included.
+    pub fn members(&self) -> &[RegistryMember] {
+        self.registry_repository.members()
+    }
+
+    /// Live entries whose secret was set before the removal of a member flagged for rotation.
+    pub fn rotation_pending(&self) -> Vec<&str> {
+        let removed = self.members().iter()
+            .filter(|member| member.rotate)
+            .filter_map(|member| member.removed)
+            .max();
+
+        self.entries.iter()
+            .filter(|(_, entry)| removed.is_some_and(|removed| entry.secret_timestamp < removed))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
 
-                    let model = EntryModel { 
+    /// Decrypts an entry secret. Returns `None` when the registry is locked.
+    pub fn decrypt_secret(&self, secret: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        self.registry_repository.decrypt_secret(secret)
+    }
+
+    /// Every revision of the entry currently or last named `name`, following renames, oldest first.
+    pub fn history(&mut self, name: &str) -> Result<Vec<EntryRevision>, Error> {
+        let mut revisions: Vec<Vec<EntryRevision>> = Vec::new();
+        let mut live = HashMap::new();
+        let mut latest = HashMap::new();
+
+        self.registry_repository.rewind()?;
+
+        while let Some(entry_operation) = self.registry_repository.read_operation()? {
+            match entry_operation {
+                EntryOperationDto::Add { hash: _, timestamp, name, description, secret } => {
+                    let revision = EntryRevision {
+                        version: 1,
                         timestamp,
-                        description, 
+                        change: EntryChange::Added,
+                        name: name.clone(),
+                        description,
                         secret,
                     };
 
-                    if result.entries.insert(name, model).is_some() {
-                        panic!("Registry is malformed. Can not add existing entry.");
-                    }
+                    live.insert(name.clone(), revisions.len());
+                    latest.insert(name, revisions.len());
+                    revisions.push(vec![revision]);
                 },
-                EntryOperationDto::Set { hash, timestamp, src_name, dst_name, dst_description, dst_secret } => {
-                    result.last_hash = hash;
+                EntryOperationDto::Set { hash: _, timestamp, src_name, dst_name, dst_description, dst_secret } => {
+                    if let Some(id) = live.remove(&src_name) {
+                        let current = revisions[id].last().unwrap();
+                        let renamed_from = dst_name.as_ref()
+                            .filter(|dst_name| **dst_name != src_name)
+                            .map(|_| src_name.clone());
+                        let name = dst_name.unwrap_or(src_name);
 
-                    if let Some(current) = result.entries.remove(&src_name) {
-                        let model = EntryModel { 
+                        let revision = EntryRevision {
+                            version: revisions[id].len() + 1,
                             timestamp,
-                            description: dst_description.unwrap_or(current.description), 
-                            secret: dst_secret.unwrap_or(current.secret),
+                            change: EntryChange::Changed {
+                                renamed_from,
+                                description: dst_description.is_some(),
+                                secret: dst_secret.is_some(),
+                            },
+                            name: name.clone(),
+                            description: dst_description.unwrap_or_else(|| current.description.clone()),
+                            secret: dst_secret.unwrap_or_else(|| current.secret.clone()),
                         };
-    
-                        result.entries.insert(dst_name.unwrap_or(src_name), model);
-                    }
-                    else {
-                        panic!("Registry is malformed. Can not set non existing entry.");
+
+                        live.insert(name.clone(), id);
+                        latest.insert(name, id);
+                        revisions[id].push(revision);
                     }
                 },
-                EntryOperationDto::Del { hash, timestamp: _, name } => {
-                    result.last_hash = hash;
+                EntryOperationDto::Del { hash: _, timestamp, name } => {
+                    if let Some(id) = live.remove(&name) {
+                        let current = revisions[id].last().unwrap();
+
+                        let revision = EntryRevision {
+                            version: revisions[id].len() + 1,
+                            timestamp,
+                            change: EntryChange::Deleted,
+                            name,
+                            description: current.description.clone(),
+                            secret: current.secret.clone(),
+                        };
 
-                    if result.entries.remove(&name).is_some() {
-                        panic!("Registry is malformed. Can not del non existing entry.");
+                        revisions[id].push(revision);
                     }
                 },
             }
         }
 
+        let result = live.get(name)
+            .or_else(|| latest.get(name))
+            .map(|id| revisions.swap_remove(*id))
+            .unwrap_or_default();
+
         Ok(result)
     }
 
-    pub fn registry_name(&self) -> &str {
-        &self.registry_repository.name
+    /// Appends a new entry. Only the encrypted `secret` is kept, wiping the plain one is left to the caller.
+    ///
+    /// Fails with [`Error::EntryExists`] if an entry named `name` already exists.
+    pub fn add(&mut self, name: String, description: String, secret: &[u8]) -> Result<(), Error> {
+        self.replay()?;
+
+        if self.entries.contains_key(&name) {
+            return Err(Error::EntryExists(name));
+        }
+
+        let entry_operation = EntryOperationDto::Add {
+            hash: [0u8; 64],
+            timestamp: now(),
+            name,
+            description,
+            secret: self.registry_repository.encrypt_secret(secret)?,
+        };
+
+        self.append(entry_operation)
     }
 
-    pub fn decrypt_secret(&self, secret: &Vec<u8>) -> Option<Vec<u8>> {
-        self.registry_repository.decrypt(&secret)
+    /// Renames an entry and/or replaces its description or secret.
+    ///
+    /// Fails with [`Error::NoSuchEntry`] if no entry named `src_name` exists 
+    /// and with [`Error::EntryExists`] if it is renamed onto another live entry.
+    pub fn set(
+        &mut self,
+        src_name: String,
+        dst_name: Option<String>,
+        dst_description: Option<String>,
+        dst_secret: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        self.replay()?;
+
+        if !self.entries.contains_key(&src_name) {
+            return Err(Error::NoSuchEntry(src_name));
+        }
+
+        if let Some(dst_name) = dst_name.as_ref().filter(|dst_name| **dst_name != src_name && self.entries.contains_key(*dst_name)) {
+            return Err(Error::EntryExists(dst_name.clone()));
+        }
+
+        let entry_operation = EntryOperationDto::Set {
+            hash: [0u8; 64],
+            timestamp: now(),
+            src_name,
+            dst_name,
+            dst_description,
+            dst_secret: dst_secret.map(|s| self.registry_repository.encrypt_secret(s)).transpose()?,
+        };
+
+        self.append(entry_operation)
     }
 
-    pub fn add(&mut self, name: String, description: String, secret: Vec<u8>) -> Result<(), io::Error> {
-        if self.entries.contains_key(&name) {
-            panic!("Can not add existing entry")
+    /// Deletes an entry.
+    ///
+    /// Fails with [`Error::NoSuchEntry`] if no entry named `name` exists.
+    pub fn del(&mut self, name: String) -> Result<(), Error> {
+        self.replay()?;
+
+        if !self.entries.contains_key(&name) {
+            return Err(Error::NoSuchEntry(name));
         }
-        
-        let secret = self.registry_repository.encrypt(&secret);
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis();
 
-        let entry = EntryModel {
-            timestamp,
-            description,
-            secret,
+        let entry_operation = EntryOperationDto::Del {
+            hash: [0u8; 64],
+            timestamp: now(),
+            name,
         };
 
-        let mut hasher = Sha3_256::new();
-        hasher.update(&self.last_hash);
-        hasher.update(&1i32.to_le_bytes());
-        hasher.update(&timestamp.to_le_bytes());
-        hasher.update(name.as_bytes());
-        hasher.update(entry.description.as_bytes());
-        hasher.update(&entry.secret);
-        self.last_hash[0..32].copy_from_slice(&hasher.finalize());
-
-        let entry_operation = EntryOperationDto::Add { 
-            hash: self.last_hash, 
-            timestamp,
-            name: name.clone(), 
-            description: entry.description.clone(), 
-            secret: entry.secret.clone(),
+        self.append(entry_operation)
+    }
+
+    pub fn export(&self, prefix: Option<&str>) -> Result<Vec<ExchangeRecord>, Error> {
+        let mut result = Vec::new();
+
+        for (name, entry) in &self.entries {
+            if prefix.is_some_and(|prefix| !name.starts_with(prefix)) {
+                continue;
+            }
+
+            let secret = self.decrypt_secret(&entry.secret)
+                .and_then(|secret| str::from_utf8(&secret).ok().map(|secret| Zeroizing::new(String::from(secret))))
+                .ok_or_else(|| Error::InvalidData(format!("Can not decrypt secret of entry {}", name)))?;
+
+            let record = ExchangeRecord {
+                name: name.clone(),
+                description: entry.description.clone(),
+                secret,
+            };
+
+            result.push(record);
+        }
+
+        Ok(result)
+    }
+
+    pub fn import(&mut self, records: Vec<ExchangeRecord>, on_conflict: ImportConflict, dry_run: bool) -> Result<ImportReport, Error> {
+        self.replay()?;
+
+        let mut names: BTreeSet<String> = self.entries.keys().cloned().collect();
+        let mut operations = Vec::new();
+
+        let mut result = ImportReport {
+            added: Vec::new(),
+            overwritten: Vec::new(),
+            renamed: Vec::new(),
+            skipped: Vec::new(),
+        };
+
+        for record in records {
+            let secret = self.registry_repository.encrypt_secret(record.secret.as_bytes())?;
+
+            let name = if !names.contains(&record.name) {
+                result.added.push(record.name.clone());
+                record.name
+            }
+            else {
+                match on_conflict {
+                    ImportConflict::Skip => {
+                        result.skipped.push(record.name);
+                        continue;
+                    },
+                    ImportConflict::Overwrite => {
+                        let entry_operation = EntryOperationDto::Set {
+                            hash: [0u8; 64],
+                            timestamp: now(),
+                            src_name: record.name.clone(),
+                            dst_name: None,
+                            dst_description: Some(record.description),
+                            dst_secret: Some(secret),
+                        };
+
+                        result.overwritten.push(record.name);
+                        operations.push(entry_operation);
+                        continue;
+                    },
+                    ImportConflict::Rename => {
+                        let name = (1..)
+                            .map(|suffix| format!("{}-{}", record.name, suffix))
+                            .find(|name| !names.contains(name))
+                            .unwrap();
+
+                        result.renamed.push((record.name, name.clone()));
+                        name
+                    },
+                }
+            };
+
+            let entry_operation = EntryOperationDto::Add {
+                hash: [0u8; 64],
+                timestamp: now(),
+                name: name.clone(),
+                description: record.description,
+                secret,
+            };
+
+            names.insert(name);
+            operations.push(entry_operation);
+        }
+
+        if !dry_run {
+            for entry_operation in operations {
+                self.append(entry_operation)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn backup(&mut self) -> Result<BackupBundle, Error> {
+        if let Some(offset) = self.torn_tail() {
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("torn record, use repair --truncate-tail before backing up"),
+            });
+        }
+
+        let (header_region, log) = self.registry_repository.regions()?;
+
+        let result = BackupBundle {
+            created: now(),
+            registry_name: String::from(self.registry_name()),
+            entries: self.entries.len() as u64,
+            operations: self.operations as u64,
+            last_hash: self.last_hash,
+            header_region,
+            encrypted_log: self.registry_repository.encrypt(&log)?,
+        };
+
+        Ok(result)
+    }
+
+    pub fn diff(&self, other: &Self) -> EntryDiff {
+        let mut result = EntryDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+
+        for (name, entry) in &other.entries {
+            match self.entries.get(name) {
+                None => result.added.push(name.clone()),
+                Some(current) if current.timestamp != entry.timestamp 
+                    || current.description != entry.description 
+                    || current.secret != entry.secret => result.changed.push(name.clone()),
+                Some(_) => {},
+            }
+        }
+
+        for name in self.entries.keys() {
+            if !other.entries.contains_key(name) {
+                result.removed.push(name.clone());
+            }
+        }
+
+        result
+    }
+
+    pub fn restore(self, restored: Self) -> Result<Option<PathBuf>, Error> {
+        self.registry_repository.replace(restored.registry_repository, true)
+    }
+
+    pub fn install(self, path: &Path) -> Result<(), Error> {
+        self.registry_repository.install(path)
+    }
+
+    pub fn discard(self) -> Result<(), Error> {
+        self.registry_repository.discard()
+    }
+
+    /// Rewrites the log into one `Add` per live entry. Logs holding authenticated operations
+    /// are only compacted unlocked, so the snapshot is authenticated as well.
+    pub fn compact(mut self, archive: bool) -> Result<CompactionReport, Error> {
+        if let Some(offset) = self.torn_tail() {
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("torn record, use repair --truncate-tail before compacting"),
+            });
+        }
+
+        if !self.registry_repository.unlocked() {
+            let authenticated = read_operations(&mut self.registry_repository)?
+                .iter()
+                .any(|entry_operation| entry_operation.hash()[32..64].iter().any(|byte| *byte != 0));
+
+            if authenticated {
+                return Err(Error::PasswordRequired(
+                    String::from("Registry has authenticated operations. Unlock it with password to compact."),
+                ));
+            }
+        }
+
+        let mut compacted = Self::empty(self.registry_repository.rewrite()?, self.verify);
+
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.timestamp);
+
+        for (name, entry) in entries {
+            let entry_operation = EntryOperationDto::Add {
+                hash: [0u8; 64],
+                timestamp: entry.timestamp,
+                name: name.clone(),
+                description: entry.description.clone(),
+                secret: entry.secret.clone(),
+            };
+
+            compacted.append(entry_operation)?;
+        }
+
+        let original_size = self.registry_repository.size()?;
+        let compacted_size = compacted.registry_repository.size()?;
+        let archive = self.registry_repository.replace(compacted.registry_repository, archive)?;
+
+        let result = CompactionReport {
+            original_size,
+            compacted_size,
+            archive,
+        };
+
+        Ok(result)
+    }
+
+    pub fn rotate_key(self, password: &str, keep_history: bool) -> Result<RotationReport, Error> {
+        let rotated = self.registry_repository.rekey(password)?;
+
+        self.reencrypt_into(rotated, keep_history)
+    }
+
+    /// Re-encrypts every secret padded with the current padding scheme of the registry.
+    pub fn pad_secrets(self, keep_history: bool) -> Result<RotationReport, Error> {
+        let padded = self.registry_repository.rewrite()?;
+
+        self.reencrypt_into(padded, keep_history)
+    }
+
+    /// Adds a team member and re-encrypts the whole history, wrapping every data key to `public_key` too.
+    /// Members only receive data keys, so authenticated registries and registries with private metadata
+    /// can not have members.
+    pub fn add_member(self, name: &str, public_key: Vec<u8>) -> Result<RotationReport, Error> {
+        if self.registry_repository.authentication_required() || self.registry_repository.metadata_private() {
+            return Err(Error::NotPermitted(String::from(
+                "Members can not be added to authenticated registries or registries with private metadata",
+            )));
+        }
+
+        let mut members = self.members().to_vec();
+
+        if members.iter().any(|member| member.active() && (member.name == name || member.public_key == public_key)) {
+            return Err(Error::InvalidInput(format!("Member {} or its public key is already in registry", name)));
+        }
+        if public_key == self.registry_repository.public_key() {
+            return Err(Error::InvalidInput(String::from("Registry key can not be added as a member")));
+        }
+
+        members.push(RegistryMember {
+            name: String::from(name),
+            public_key,
+            added: now(),
+            removed: None,
+            rotate: false,
+        });
+
+        let target = self.registry_repository.with_members(members)?;
+
+        self.reencrypt_into(target, true)
+    }
+
+    /// Removes a team member and re-encrypts the whole history with new data keys it is never given.
+    /// With `rotate` live secrets it could read are reported by `rotation_pending` until they are changed.
+    pub fn remove_member(self, name: &str, rotate: bool) -> Result<RotationReport, Error> {
+        let mut members = self.members().to_vec();
+
+        let member = members.iter_mut()
+            .find(|member| member.active() && member.name == name)
+            .ok_or_else(|| Error::InvalidInput(format!("Member {} does not exist", name)))?;
+
+        member.removed = Some(now());
+        member.rotate = rotate;
+
+        let target = self.registry_repository.with_members(members)?;
+
+        self.reencrypt_into(target, true)
+    }
+
+    /// Re-encrypts live entries, or the whole history when `keep_history` is set, into `rotated`
+    /// and swaps the registry with it once every secret decrypts the same.
+    fn reencrypt_into(mut self, rotated: RegistryRepository, keep_history: bool) -> Result<RotationReport, Error> {
+        let mut rotated = Self::empty(rotated, true);
+
+        if keep_history {
+            self.registry_repository.rewind()?;
+
+            while let Some(entry_operation) = self.registry_repository.read_operation()? {
+                let entry_operation = match entry_operation {
+                    EntryOperationDto::Add { hash: _, timestamp, name, description, secret } => EntryOperationDto::Add {
+                        hash: [0u8; 64],
+                        timestamp,
+                        name,
+                        description,
+                        secret: self.reencrypt(&rotated, &secret)?,
+                    },
+                    EntryOperationDto::Set { hash: _, timestamp, src_name, dst_name, dst_description, dst_secret } => EntryOperationDto::Set {
+                        hash: [0u8; 64],
+                        timestamp,
+                        src_name,
+                        dst_name,
+                        dst_description,
+                        dst_secret: dst_secret.map(|secret| self.reencrypt(&rotated, &secret)).transpose()?,
+                    },
+                    EntryOperationDto::Del { hash: _, timestamp, name } => EntryOperationDto::Del {
+                        hash: [0u8; 64],
+                        timestamp,
+                        name,
+                    },
+                };
+
+                rotated.append(entry_operation)?;
+            }
+        }
+        else {
+            let mut entries: Vec<_> = self.entries.iter().collect();
+            entries.sort_by_key(|(_, entry)| entry.timestamp);
+
+            for (name, entry) in entries {
+                let entry_operation = EntryOperationDto::Add {
+                    hash: [0u8; 64],
+                    timestamp: entry.timestamp,
+                    name: name.clone(),
+                    description: entry.description.clone(),
+                    secret: self.reencrypt(&rotated, &entry.secret)?,
+                };
+
+                rotated.append(entry_operation)?;
+            }
+        }
+
+        let mut check = Self::empty(rotated.registry_repository, true);
+        check.registry_repository.rewind()?;
+        check.replay()?;
+
+        for (name, entry) in &self.entries {
+            let rotated_secret = check.entries.get(name)
+                .and_then(|rotated_entry| check.decrypt_secret(&rotated_entry.secret));
+
+            if rotated_secret.is_none() || rotated_secret != self.decrypt_secret(&entry.secret) {
+                return Err(Error::InvalidData(format!("Rotated registry verification failed for entry {}", name)));
+            }
+        }
+
+        let result = RotationReport {
+            entries: check.entries.len(),
+            operations: check.operations,
         };
 
-        self.entries.insert(name, entry);
+        self.registry_repository.replace(check.registry_repository, false)?;
+
+        Ok(result)
+    }
+
+    pub fn revert(self, name: Option<&str>, until: u128) -> Result<usize, Error> {
+        let mut registry_repository = self.registry_repository;
+        registry_repository.rewind()?;
+
+        let mut past = Self::empty(registry_repository, self.verify);
+        past.until = Some(until);
+        past.replay()?;
+
+        let mut registry_repository = past.registry_repository;
+        registry_repository.rewind()?;
+
+        let mut present = Self::empty(registry_repository, self.verify);
+        present.replay()?;
+
+        let mut names: Vec<String> = past.entries.keys()
+            .chain(present.entries.keys())
+            .filter(|entry_name| name.is_none_or(|name| name == entry_name.as_str()))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let mut result = 0;
+
+        for name in names {
+            let entry_operation = match (past.entries.remove(&name), present.entries.get(&name)) {
+                (None, Some(_)) => EntryOperationDto::Del {
+                    hash: [0u8; 64],
+                    timestamp: now(),
+                    name,
+                },
+                (Some(entry), None) => EntryOperationDto::Add {
+                    hash: [0u8; 64],
+                    timestamp: now(),
+                    name,
+                    description: entry.description,
+                    secret: entry.secret,
+                },
+                (Some(entry), Some(current)) if entry.description != current.description || entry.secret != current.secret => EntryOperationDto::Set {
+                    hash: [0u8; 64],
+                    timestamp: now(),
+                    dst_description: Some(entry.description).filter(|description| *description != current.description),
+                    dst_secret: Some(entry.secret).filter(|secret| *secret != current.secret),
+                    src_name: name,
+                    dst_name: None,
+                },
+                _ => continue,
+            };
+
+            present.append(entry_operation)?;
+            result += 1;
+        }
+
+        Ok(result)
+    }
+
+    pub fn sync<F>(self, mut remote_repository: RegistryRepository, mut resolve: F) -> Result<SyncReport, Error> 
+    where 
+        F: FnMut(&SyncConflict) -> Result<SyncSide, Error>,
+    {
+        if remote_repository.public_key() != self.registry_repository.public_key() {
+            return Err(Error::InvalidInput(String::from("Registries do not share the same key and can not be synchronized")));
+        }
+
+        remote_repository.unlock_with(&self.registry_repository);
+
+        let remote = Self::new(remote_repository, true)?;
+
+        if let Some(offset) = self.torn_tail().or(remote.torn_tail()) {
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("torn record, repair it before synchronization"),
+            });
+        }
+
+        let mut local_repository = self.registry_repository;
+        let mut remote_repository = remote.registry_repository;
+
+        let mut local_operations = read_operations(&mut local_repository)?;
+        let mut remote_operations = read_operations(&mut remote_repository)?;
+
+        let common = local_operations.iter()
+            .zip(&remote_operations)
+            .take_while(|(local, remote)| local.hash() == remote.hash())
+            .count();
+
+        // Merged operations are tagged again, so remote ones must carry a valid tag of their own.
+        if let Some((index, offset)) = remote.unauthenticated.iter().find(|(index, _)| *index >= common) {
+            return Err(Error::IntegrityViolation {
+                operation: *index,
+                offset: *offset,
+                reason: String::from("remote operation is not authenticated"),
+            });
+        }
+
+        let local_branch = local_operations.split_off(common);
+        let remote_branch = remote_operations.split_off(common);
+
+        let mut result = SyncReport {
+            common,
+            local: local_branch.len(),
+            remote: remote_branch.len(),
+            merged: 0,
+            conflicts: 0,
+            skipped: 0,
+        };
+
+        if local_branch.is_empty() && remote_branch.is_empty() {
+            return Ok(result);
+        }
+
+        let fast_forward = local_branch.is_empty() || remote_branch.is_empty();
+
+        let local_touched = touched_entries(&local_branch);
+        let remote_touched = touched_entries(&remote_branch);
+
+        let mut local_dropped = BTreeSet::new();
+        let mut remote_dropped = BTreeSet::new();
+
+        for (name, local_timestamp) in &local_touched {
+            if let Some(remote_timestamp) = remote_touched.get(name) {
+                let conflict = SyncConflict {
+                    name: name.clone(),
+                    local_timestamp: *local_timestamp,
+                    remote_timestamp: *remote_timestamp,
+                };
+
+                match resolve(&conflict)? {
+                    SyncSide::Local => remote_dropped.insert(conflict.name),
+                    SyncSide::Remote => local_dropped.insert(conflict.name),
+                };
+
+                result.conflicts += 1;
+            }
+        }
+
+        let mut branch: Vec<_> = local_branch.into_iter()
+            .filter(|entry_operation| !touches_any(entry_operation, &local_dropped))
+            .chain(remote_branch.into_iter().filter(|entry_operation| !touches_any(entry_operation, &remote_dropped)))
+            .collect();
+
+        // A one sided branch is copied in log order with its hashes, anything reordered or skipped is chained again.
+        if !fast_forward {
+            branch.sort_by_key(|entry_operation| entry_operation.timestamp());
+        }
+
+        let mut verbatim = fast_forward;
+
+        let mut merged = Self::empty(local_repository.rewrite()?, self.verify);
+
+        for entry_operation in local_operations {
+            merged.registry_repository.write_operation(&entry_operation)?;
+            merged.apply(entry_operation).map_err(inconsistent)?;
+        }
+
+        for entry_operation in branch {
+            if !merged.applicable(&entry_operation) {
+                result.skipped += 1;
+                verbatim = false;
+                continue;
+            }
+
+            if verbatim {
+                merged.registry_repository.write_operation(&entry_operation)?;
+                merged.apply(entry_operation).map_err(inconsistent)?;
+            }
+            else {
+                merged.append(entry_operation)?;
+            }
+
+            result.merged += 1;
+        }
+
+        let mut remote_replacement = remote_repository.rewrite()?;
+        for entry_operation in read_operations(&mut merged.registry_repository)? {
+            remote_replacement.write_operation(&entry_operation)?;
+        }
+
+        local_repository.replace(merged.registry_repository, false)?;
+        remote_repository.replace(remote_replacement, false)?;
+
+        Ok(result)
+    }
+
+    fn reencrypt(&self, rotated: &Self, secret: &[u8]) -> Result<Vec<u8>, Error> {
+        let secret = self.decrypt_secret(secret).ok_or_else(|| Error::InvalidData(
+            String::from("Can not decrypt secret with current registry key"),
+        ))?;
+
+        rotated.registry_repository.encrypt_secret(&secret)
+    }
+
+    fn replay(&mut self) -> Result<(), Error> {
+        loop {
+            let index = self.operations;
+            let offset = self.registry_repository.position()?;
+
+            let Some(entry_operation) = self.registry_repository.read_operation()? else {
+                break;
+            };
+
+            let timestamp = entry_operation.timestamp();
+
+            if self.until.is_some_and(|until| timestamp > until) {
+                break;
+            }
+
+            let required = self.registry_repository.authentication_required();
+
+            if self.verify || (required && self.registry_repository.unlocked()) {
+                let digest = operation_hash(&self.last_hash, &entry_operation);
+                if digest != entry_operation.hash()[0..32] {
+                    return Err(malformed(index, offset, timestamp, "hash chain is broken"));
+                }
+
+                if let Some(tag) = self.registry_repository.authentication_tag(&digest) {
+                    let stored_tag = &entry_operation.hash()[32..64];
+
+                    if tag == stored_tag {
+                        self.authenticated += 1;
+                    }
+                    else if required {
+                        return Err(malformed(index, offset, timestamp, "operation is not authenticated"));
+                    }
+                    else if stored_tag.iter().any(|byte| *byte != 0) {
+                        return Err(malformed(index, offset, timestamp, "operation authentication tag does not match"));
+                    }
+                    else {
+                        self.unauthenticated.push((index, offset));
+                    }
+                }
+            }
+
+            if let Err(reason) = self.apply(entry_operation) {
+                return Err(malformed(index, offset, timestamp, reason));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append(&mut self, mut entry_operation: EntryOperationDto) -> Result<(), Error> {
+        if self.until.is_some() {
+            return Err(Error::NotPermitted(String::from("Registry view at point in time is read only")));
+        }
+
+        let digest = operation_hash(&self.last_hash, &entry_operation);
+        let tag = self.registry_repository.authentication_tag(&digest);
+
+        if tag.is_none() && self.registry_repository.authentication_required() {
+            return Err(Error::PasswordRequired(
+                String::from("Registry requires authenticated operations. Unlock it with password."),
+            ));
+        }
+
+        let mut hash = [0u8; 64];
+        hash[0..32].copy_from_slice(&digest);
+        hash[32..64].copy_from_slice(&tag.unwrap_or([0u8; 32]));
+        entry_operation.set_hash(hash);
 
         self.registry_repository.write_operation(&entry_operation)?;
 
+        if tag.is_some() {
+            self.authenticated += 1;
+        }
+
+        self.apply(entry_operation).map_err(inconsistent)
+    }
+
+    fn applicable(&self, entry_operation: &EntryOperationDto) -> bool {
+        match entry_operation {
+            EntryOperationDto::Add { name, .. } => !self.entries.contains_key(name),
+            EntryOperationDto::Set { src_name, .. } => self.entries.contains_key(src_name),
+            EntryOperationDto::Del { name, .. } => self.entries.contains_key(name),
+        }
+    }
+
+    fn apply(&mut self, entry_operation: EntryOperationDto) -> Result<(), &'static str> {
+        match entry_operation {
+            EntryOperationDto::Add { hash, timestamp, name, description, secret } => {
+                let model = EntryModel {
+                    timestamp,
+                    secret_timestamp: timestamp,
+                    description,
+                    secret,
+                };
+
+                if self.entries.insert(name, model).is_some() {
+                    return Err("can not add existing entry");
+                }
+
+                self.last_hash = hash;
+            },
+            EntryOperationDto::Set { hash, timestamp, src_name, dst_name, dst_description, dst_secret } => {
+                if let Some(current) = self.entries.remove(&src_name) {
+                    let model = EntryModel {
+                        timestamp,
+                        secret_timestamp: if dst_secret.is_some() { timestamp } else { current.secret_timestamp },
+                        description: dst_description.unwrap_or(current.description),
+                        secret: dst_secret.unwrap_or(current.secret),
+                    };
+
+                    self.entries.insert(dst_name.unwrap_or(src_name), model);
+                }
+                else {
+                    return Err("can not set non existing entry");
+                }
+
+                self.last_hash = hash;
+            },
+            EntryOperationDto::Del { hash, timestamp: _, name } => {
+                if self.entries.remove(&name).is_none() {
+                    return Err("can not del non existing entry");
+                }
+
+                self.last_hash = hash;
+            },
+        }
+
+        self.operations += 1;
+
         Ok(())
     }
+}
 
-    pub fn set(
-        &mut self, 
-        src_name: String, 
-        dst_name: Option<String>, 
-        dst_description: Option<String>, 
-        dst_secret: Option<Vec<u8>>,
-    ) -> Result<(), io::Error> {
-        let current = self.entries.remove(&src_name);
-        
-        if let Some(current) = current {
-            let dst_secret = dst_secret.map(|s| self.registry_repository.encrypt(&s));
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_millis();
-
-            let mut hasher = Sha3_256::new();
-            hasher.update(&self.last_hash);
-            hasher.update(&2i32.to_le_bytes());
-            hasher.update(&timestamp.to_le_bytes());
+fn operation_hash(last_hash: &[u8; 64], entry_operation: &EntryOperationDto) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(last_hash);
+
+    match entry_operation {
+        EntryOperationDto::Add { hash: _, timestamp, name, description, secret } => {
+            hasher.update(1i32.to_le_bytes());
+            hasher.update(timestamp.to_le_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update(description.as_bytes());
+            hasher.update(secret);
+        },
+        EntryOperationDto::Set { hash: _, timestamp, src_name, dst_name, dst_description, dst_secret } => {
+            hasher.update(2i32.to_le_bytes());
+            hasher.update(timestamp.to_le_bytes());
             hasher.update(src_name.as_bytes());
-            if let Some(dst_name) = &dst_name {
+            if let Some(dst_name) = dst_name {
                 hasher.update(dst_name.as_bytes());
             }
-            if let Some(dst_description) = &dst_description {
+            if let Some(dst_description) = dst_description {
                 hasher.update(dst_description.as_bytes());
             }
-            if let Some(dst_secret) = &dst_secret {
-                hasher.update(&dst_secret);
-            }
-            self.last_hash[0..32].copy_from_slice(&hasher.finalize());
-    
-            let entry_operation = EntryOperationDto::Set { 
-                hash: self.last_hash, 
-                timestamp,
-                src_name: src_name.clone(), 
-                dst_name: dst_name.clone(), 
-                dst_description: dst_description.clone(), 
-                dst_secret: dst_secret.clone(),
-            };
+            if let Some(dst_secret) = dst_secret {
+                hasher.update(dst_secret);
+            }
+        },
+        EntryOperationDto::Del { hash: _, timestamp, name } => {
+            hasher.update(3i32.to_le_bytes());
+            hasher.update(timestamp.to_le_bytes());
+            hasher.update(name.as_bytes());
+        },
+    }
 
-            let new_entry = EntryModel {
-                timestamp,
-                description: dst_description.unwrap_or(current.description),
-                secret: dst_secret.unwrap_or(current.secret),
-            };
-    
-            self.entries.insert(dst_name.unwrap_or(src_name), new_entry);
-    
-            self.registry_repository.write_operation(&entry_operation)?;
-    
-            Ok(())
+    hasher.finalize().into()
+}
+
+fn read_operations(registry_repository: &mut RegistryRepository) -> Result<Vec<EntryOperationDto>, Error> {
+    let mut result = Vec::new();
+
+    registry_repository.rewind()?;
+    while let Some(entry_operation) = registry_repository.read_operation()? {
+        result.push(entry_operation);
+    }
+
+    Ok(result)
+}
+
+fn touched_names(entry_operation: &EntryOperationDto) -> Vec<&String> {
+    match entry_operation {
+        EntryOperationDto::Add { name, .. } => vec![name],
+        EntryOperationDto::Set { src_name, dst_name, .. } => [Some(src_name), dst_name.as_ref()].into_iter().flatten().collect(),
+        EntryOperationDto::Del { name, .. } => vec![name],
+    }
+}
+
+fn touched_entries(entry_operations: &[EntryOperationDto]) -> BTreeMap<String, u128> {
+    let mut result = BTreeMap::new();
+
+    for entry_operation in entry_operations {
+        for name in touched_names(entry_operation) {
+            result.insert(name.clone(), entry_operation.timestamp());
         }
-        else {
-            panic!("Can not set non existing entry")
-        }    
     }
 
-    pub fn del(&mut self, name: String) -> Result<(), io::Error> {
-        if !self.entries.remove(&name).is_some() {
-            panic!("Can not del non existing entry")
+    result
+}
+
+fn touches_any(entry_operation: &EntryOperationDto, names: &BTreeSet<String>) -> bool {
+    touched_names(entry_operation).into_iter().any(|name| names.contains(name))
+}
+
+fn malformed(index: usize, offset: u64, timestamp: u128, reason: &str) -> Error {
+    Error::IntegrityViolation {
+        operation: index,
+        offset,
+        reason: format!("{} (timestamp {})", reason, timestamp),
+    }
+}
+
+fn inconsistent(reason: &str) -> Error {
+    Error::InvalidData(format!("Registry operation is inconsistent: {}", reason))
+}
+
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::registries::{KdfParameters, MemoryStorage, RegistryStorage};
+
+    use super::*;
+
+    fn init(storage: &MemoryStorage) -> EntryService {
+        let registry = RegistryRepository::init_with(
+            Box::new(storage.clone()), "test", "password", true, false, KdfParameters::argon2id(8, 1),
+        ).unwrap();
+
+        EntryService::new(registry, true).unwrap()
+    }
+
+    fn open(storage: &MemoryStorage) -> EntryService {
+        let mut registry = RegistryRepository::with_storage(Box::new(storage.clone())).unwrap();
+        registry.unlock("password").unwrap();
+
+        EntryService::new(registry, true).unwrap()
+    }
+
+    fn replicate(storage: &MemoryStorage) -> MemoryStorage {
+        let mut source = storage.clone();
+        let mut result = MemoryStorage::new();
+        result.write_header(&source.read_header().unwrap()).unwrap();
+
+        source.rewind().unwrap();
+        while let Some(entry_operation) = source.read_operation().unwrap() {
+            result.append_operation(&entry_operation).unwrap();
+        }
+
+        result
+    }
+
+    fn hashes(storage: &MemoryStorage) -> Vec<[u8; 64]> {
+        let mut storage = storage.clone();
+        let mut result = Vec::new();
+
+        storage.rewind().unwrap();
+        while let Some(entry_operation) = storage.read_operation().unwrap() {
+            result.push(*entry_operation.hash());
         }
 
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_millis();
-        
-        let mut hasher = Sha3_256::new();
-        hasher.update(&self.last_hash);
-        hasher.update(&3i32.to_le_bytes());
-        hasher.update(&timestamp.to_le_bytes());
-        hasher.update(name.as_bytes());
-        self.last_hash[0..32].copy_from_slice(&hasher.finalize());
+        result
+    }
 
-        let entry_operation = EntryOperationDto::Del { 
-            hash: self.last_hash, 
+    /// Appends operations with given timestamps, as written by a replica whose clock went backwards.
+    fn add_at(entries: &mut EntryService, name: &str, timestamp: u128) {
+        let entry_operation = EntryOperationDto::Add {
+            hash: [0u8; 64],
             timestamp,
-            name: name.clone(), 
+            name: String::from(name),
+            description: String::new(),
+            secret: entries.registry_repository.encrypt_secret(b"secret").unwrap(),
         };
 
-        self.registry_repository.write_operation(&entry_operation)?;
+        entries.append(entry_operation).unwrap();
+    }
 
-        Ok(())
+    fn describe_at(entries: &mut EntryService, name: &str, timestamp: u128) {
+        let entry_operation = EntryOperationDto::Set {
+            hash: [0u8; 64],
+            timestamp,
+            src_name: String::from(name),
+            dst_name: None,
+            dst_description: Some(String::from("changed")),
+            dst_secret: None,
+        };
+
+        entries.append(entry_operation).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fast_forward_keeps_log_order_of_branch() {
+        let local = MemoryStorage::new();
+        add_at(&mut init(&local), "db", 100);
+        let remote = replicate(&local);
+
+        let mut entries = open(&local);
+        add_at(&mut entries, "web", 300);
+        describe_at(&mut entries, "web", 200);
+        let report = entries.sync(RegistryRepository::with_storage(Box::new(remote.clone())).unwrap(), |_| unreachable!()).unwrap();
+
+        assert_eq!((report.merged, report.skipped), (2, 0));
+        assert_eq!(hashes(&local), hashes(&remote));
+        assert_eq!(open(&remote).get("web").unwrap().description, "changed");
+    }
+
+    #[test]
+    fn merge_chains_again_reordered_and_skipped_operations() {
+        let local = MemoryStorage::new();
+        add_at(&mut init(&local), "db", 100);
+        let remote = replicate(&local);
+
+        let mut entries = open(&local);
+        add_at(&mut entries, "web", 300);
+        describe_at(&mut entries, "web", 200);
+
+        add_at(&mut open(&remote), "mail", 250);
+
+        let report = entries.sync(RegistryRepository::with_storage(Box::new(remote.clone())).unwrap(), |_| unreachable!()).unwrap();
+
+        assert_eq!((report.merged, report.skipped), (2, 1));
+        assert_eq!(hashes(&local), hashes(&remote));
+
+        let entries = open(&remote);
+        assert_eq!(entries.operations(), 3);
+        assert_eq!(entries.authenticated_operations(), Some(3));
+        assert_eq!(entries.get("web").unwrap().description, "");
+    }
+}
@@ -0,0 +1,11 @@
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImportConflict {
+    /// Keep existing entry
+    Skip,
+
+    /// Replace description and secret of existing entry
+    Overwrite,
+
+    /// Add imported entry under free name with numeric suffix
+    Rename,
+}
@@ -0,0 +1,6 @@
+pub struct ImportReport {
+    pub added: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub renamed: Vec<(String, String)>,
+    pub skipped: Vec<String>,
+}
@@ -1,5 +1,25 @@
+mod compaction_report;
+mod entry_change;
+mod entry_diff;
 mod entry_model;
+mod entry_revision;
 mod entry_service;
+mod import_conflict;
+mod import_report;
+mod rotation_report;
+mod sync_conflict;
+mod sync_report;
+mod sync_side;
 
+pub use compaction_report::CompactionReport;
+pub use entry_change::EntryChange;
+pub use entry_diff::EntryDiff;
 pub use entry_model::EntryModel;
-pub use entry_service::EntryService;
\ No newline at end of file
+pub use entry_revision::EntryRevision;
+pub use entry_service::EntryService;
+pub use import_conflict::ImportConflict;
+pub use import_report::ImportReport;
+pub use rotation_report::RotationReport;
+pub use sync_conflict::SyncConflict;
+pub use sync_report::SyncReport;
+pub use sync_side::SyncSide;
@@ -0,0 +1,4 @@
+pub struct RotationReport {
+    pub entries: usize,
+    pub operations: usize,
+}
@@ -0,0 +1,5 @@
+pub struct SyncConflict {
+    pub name: String,
+    pub local_timestamp: u128,
+    pub remote_timestamp: u128,
+}
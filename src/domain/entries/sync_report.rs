@@ -0,0 +1,8 @@
+pub struct SyncReport {
+    pub common: usize,
+    pub local: usize,
+    pub remote: usize,
+    pub merged: usize,
+    pub conflicts: usize,
+    pub skipped: usize,
+}
@@ -0,0 +1,5 @@
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SyncSide {
+    Local,
+    Remote,
+}
@@ -1,3 +1,2 @@
-pub mod passwords;
 pub mod entries;
-pub mod secrets;
\ No newline at end of file
+pub mod secrets;
@@ -1,4 +0,0 @@
-mod ensure_password;
-
-pub use ensure_password::ensure_password;
-pub use ensure_password::ensure_new_password;
\ No newline at end of file
@@ -1,10 +1,17 @@
 use rand::{RngCore};
 use rand_core::OsRng;
+use zeroize::Zeroizing;
 
-pub fn generate(format: &str) -> String {
-    let (len_str, cfg_str) = format.split_once(':').unwrap();
+use crate::errors::Error;
 
-    let len = usize::from_str_radix(len_str, 10).unwrap();
+/// Generates a random secret from a `<length>:<pools>` format, where pools are any of
+/// `l` (lowercase), `u` (uppercase), `d` (digits) and `s` (symbols).
+///
+/// Fails with [`Error::InvalidInput`] if the format is malformed or selects no pool.
+pub fn generate(format: &str) -> Result<Zeroizing<String>, Error> {
+    let (len_str, cfg_str) = format.split_once(':').ok_or_else(|| invalid_format(format))?;
+
+    let len: usize = len_str.parse().map_err(|_| invalid_format(format))?;
 
     let mut pool = Vec::<char>::new();
 
@@ -24,20 +31,28 @@ pub fn generate(format: &str) -> String {
         pool.extend(SYMBOLS_POOL.chars());
     }
 
-    let mut dest = vec![0u8; len];
+    if pool.is_empty() {
+        return Err(invalid_format(format));
+    }
+
+    let mut dest = Zeroizing::new(vec![0u8; len]);
     OsRng.fill_bytes(&mut dest);
 
-    let mut result = String::with_capacity(len);
+    let mut result = Zeroizing::new(String::with_capacity(len));
 
     let mut acc = 0usize;
-    for i in 0..len {
-        let index = (acc + dest[i] as usize) % pool.len();
-        acc += dest[i] as usize - index;
+    for &byte in dest.iter() {
+        let index = (acc + byte as usize) % pool.len();
+        acc += byte as usize - index;
 
         result.push(pool[index]);
     }
 
-    result
+    Ok(result)
+}
+
+fn invalid_format(format: &str) -> Error {
+    Error::InvalidInput(format!("Invalid generator format {}. Use <length>:<pools> like 24:dslu.", format))
 }
 
 const LOWERCASE_LETTERS_POOL: &str = "abcdefghijklmnopqrstuvwxyz";
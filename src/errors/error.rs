@@ -0,0 +1,132 @@
+use std::{error, fmt, io};
+
+/// Errors returned by registry, entry and exchange operations.
+///
+/// Every variant maps to a distinct process exit code, see [`Error::exit_code`].
+#[derive(Debug)]
+pub enum Error {
+    /// File system or terminal failure.
+    Io(io::Error),
+    /// Registry or exchange file password is wrong.
+    WrongPassword,
+    /// Operation requires the registry to be unlocked with its password.
+    PasswordRequired(String),
+    /// No live entry has the given name.
+    NoSuchEntry(String),
+    /// A live entry with the given name already exists.
+    EntryExists(String),
+    /// No registry exists at the given name or path.
+    NoSuchRegistry(String),
+    /// A registry already exists at the given path.
+    RegistryExists(String),
+    /// Registry lock was not acquired within the lock timeout.
+    RegistryLocked,
+    /// Registry log record at `offset` is malformed or torn.
+    CorruptLog { offset: u64, reason: String },
+    /// Operation at `offset` breaks the hash chain or is not authenticated.
+    IntegrityViolation { operation: usize, offset: u64, reason: String },
+    /// Registry or backup bundle format version is not supported.
+    UnsupportedVersion(i64),
+    /// File content is malformed: registry header, backup bundle or exchange file.
+    InvalidData(String),
+    /// Argument is invalid: registry name, generator format, KDF parameters or configuration.
+    InvalidInput(String),
+    /// Operation is not allowed in the current state, such as writing a read only view.
+    NotPermitted(String),
+    /// Operation is not supported, such as exporting to an import only format.
+    Unsupported(String),
+    /// User declined to confirm the operation.
+    Declined(String),
+    /// Configuration key is not set.
+    NoSuchKey(String),
+}
+
+impl Error {
+    /// Process exit code of the error. Code 2 is left to command line usage errors.
+    ///
+    /// | Code | Error |
+    /// |------|-------|
+    /// | 1    | `Io` |
+    /// | 3    | `WrongPassword` |
+    /// | 4    | `PasswordRequired` |
+    /// | 5    | `NoSuchEntry` |
+    /// | 6    | `EntryExists` |
+    /// | 7    | `NoSuchRegistry` |
+    /// | 8    | `RegistryExists` |
+    /// | 9    | `RegistryLocked` |
+    /// | 10   | `CorruptLog` |
+    /// | 11   | `IntegrityViolation` |
+    /// | 12   | `UnsupportedVersion` |
+    /// | 13   | `InvalidData` |
+    /// | 14   | `InvalidInput` |
+    /// | 15   | `NotPermitted` |
+    /// | 16   | `Unsupported` |
+    /// | 17   | `Declined` |
+    /// | 18   | `NoSuchKey` |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Io(_) => 1,
+            Self::WrongPassword => 3,
+            Self::PasswordRequired(_) => 4,
+            Self::NoSuchEntry(_) => 5,
+            Self::EntryExists(_) => 6,
+            Self::NoSuchRegistry(_) => 7,
+            Self::RegistryExists(_) => 8,
+            Self::RegistryLocked => 9,
+            Self::CorruptLog { .. } => 10,
+            Self::IntegrityViolation { .. } => 11,
+            Self::UnsupportedVersion(_) => 12,
+            Self::InvalidData(_) => 13,
+            Self::InvalidInput(_) => 14,
+            Self::NotPermitted(_) => 15,
+            Self::Unsupported(_) => 16,
+            Self::Declined(_) => 17,
+            Self::NoSuchKey(_) => 18,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::WrongPassword => write!(f, "Wrong password"),
+            Self::PasswordRequired(reason) => write!(f, "{}", reason),
+            Self::NoSuchEntry(name) => write!(f, "Entry {} does not exist", name),
+            Self::EntryExists(name) => write!(f, "Entry {} already exists", name),
+            Self::NoSuchRegistry(name) => write!(f, "Registry {} does not exist", name),
+            Self::RegistryExists(name) => write!(f, "Registry {} already exists", name),
+            Self::RegistryLocked => write!(f, "Registry is locked by another process"),
+            Self::CorruptLog { offset, reason } => write!(f, "Registry log is corrupt at offset {}: {}", offset, reason),
+            Self::IntegrityViolation { operation, offset, reason } => write!(
+                f, 
+                "Registry integrity violated at operation {} (offset {}): {}", 
+                operation, 
+                offset, 
+                reason,
+            ),
+            Self::UnsupportedVersion(version) => write!(f, "Unsupported format version {}", version),
+            Self::InvalidData(reason) => write!(f, "{}", reason),
+            Self::InvalidInput(reason) => write!(f, "{}", reason),
+            Self::NotPermitted(reason) => write!(f, "{}", reason),
+            Self::Unsupported(reason) => write!(f, "{}", reason),
+            Self::Declined(reason) => write!(f, "{}", reason),
+            Self::NoSuchKey(key) => write!(f, "Configuration key {} is not set", key),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
@@ -0,0 +1,90 @@
+//! Hash-chained, encrypted secret registries.
+//!
+//! A registry holds a header with the registry key pair and an append-only
+//! log of entry operations. Secrets are encrypted with the registry public
+//! key, so entries can be added without a password while reading secrets
+//! requires unlocking the private key.
+//!
+//! Registries are kept in a [`RegistryStorage`]: a single file by default,
+//! a SQLite database for large registries, or memory for tests and embedding
+//! through [`RegistryRepository::init_with`] and [`RegistryRepository::with_storage`].
+//!
+//! ```no_run
+//! use std::{path::Path, time::Duration};
+//! use enigmatic::{EntryService, LockMode, RegistryRepository};
+//!
+//! let path = Path::new("registry");
+//! let registry = RegistryRepository::open_decrypt(path, "password", LockMode::Exclusive, Duration::from_secs(10))?;
+//! let mut entries = EntryService::new(registry, true)?;
+//!
+//! let secret = enigmatic::generate("24:dslu")?;
+//! entries.add(String::from("db"), String::from("Production"), secret.as_bytes())?;
+//!
+//! for (name, entry) in entries.entries() {
+//!     let secret = entries.decrypt_secret(&entry.secret);
+//!     println!("{}: {:?}", name, secret.as_deref().map(|secret| std::str::from_utf8(secret)));
+//! }
+//! # Ok::<(), enigmatic::Error>(())
+//! ```
+//!
+//! Registries created with private metadata also encrypt entry names and
+//! descriptions, so reading their log requires the registry to be unlocked,
+//! see [`RegistryRepository::metadata_private`].
+//!
+//! Secrets are padded to the buckets of a [`SecretPadding`] scheme before
+//! encryption, see [`RegistryRepository::set_padding`].
+//!
+//! Team registries list [`RegistryMember`]s. Their secrets are sealed with
+//! a data key wrapped to every active member, who unlock them with their own
+//! registry through [`RegistryRepository::unlock_as`].
+//!
+//! Decrypted secrets, generated secrets and the unlocked private key are held
+//! in [`Zeroizing`] buffers that are wiped on drop. While a registry is
+//! unlocked its private key is locked in memory where the OS allows and core
+//! dumps of the process are disabled.
+//!
+//! Items re-exported from the crate root form the public API and follow
+//! semantic versioning. That includes [`RegistryStorage`] and
+//! [`EntryOperationDto`] for custom backends, but not the byte layout of
+//! registry files, which is internal.
+
+mod domain;
+mod errors;
+mod storage;
+
+pub use domain::entries::{
+    CompactionReport,
+    EntryChange,
+    EntryDiff,
+    EntryModel,
+    EntryRevision,
+    EntryService,
+    ImportConflict,
+    ImportReport,
+    RotationReport,
+    SyncConflict,
+    SyncReport,
+    SyncSide,
+};
+pub use domain::secrets::generate;
+pub use errors::Error;
+pub use storage::backups::BackupBundle;
+pub use storage::entries::EntryOperationDto;
+pub use storage::exchange::{ExchangeContent, ExchangeFormat, ExchangeRecord};
+pub use storage::registries::{
+    FileStorage,
+    KdfParameters,
+    LockMode,
+    MemoryStorage,
+    RegistryCatalog,
+    RegistryMember,
+    RegistryRepository,
+    RegistryStorage,
+    SecretPadding,
+    SqliteStorage,
+    DEFAULT_KDF_ITERATIONS,
+    DEFAULT_KDF_MEMORY,
+    KDF_ARGON2ID,
+    MIN_PADDED_SIZE,
+};
+pub use zeroize::Zeroizing;
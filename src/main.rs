@@ -1,27 +1,102 @@
+use std::{fs::{self, File}, io::{self, Write}, path::Path, process, str, thread, time::Duration};
+
 use clap::Parser;
 use cli::Cli;
 use clipboard::{ClipboardContext, ClipboardProvider};
-use domain::{entries::EntryService, secrets, passwords::ensure_password};
-use storage::registries::RegistryRepository;
+use chrono::{Local, TimeZone};
+use enigmatic::{EntryService, EntryModel, EntryChange, Error, ExchangeFormat, ImportReport, SyncSide};
+use enigmatic::{BackupBundle, RegistryMember, RegistryRepository, RegistryCatalog, RegistryStorage, FileStorage, SqliteStorage, LockMode, KdfParameters, KDF_ARGON2ID, DEFAULT_KDF_MEMORY, DEFAULT_KDF_ITERATIONS};
+
+use config::{Config, OutputFormat};
+use serde_json::json;
+use zeroize::Zeroizing;
 
-use crate::{cli::CliCommand, domain::passwords::ensure_new_password};
+use crate::{cli::{CliCommand, ConfigCommand, InitArgs, MemberCommand, MetadataMode, RegistryCommand, StorageBackend, SyncPreference}, passwords::{ensure_password, ensure_new_password, ensure_changed_password, ensure_file_password, ensure_new_file_password}};
 
 mod cli;
-mod domain;
-mod storage;
+mod config;
+mod passwords;
 
 fn main() {
     let cli = Cli::parse();
 
-    let storage = if let Some(path) = cli.storage {
+    if let Err(error) = run(cli) {
+        eprintln!("Error: {}", error);
+        process::exit(error.exit_code());
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Error> {
+    let config_path = cli.config.unwrap_or_else(Config::default_path);
+    let file_config = Config::load(&config_path)?;
+
+    let mut config = file_config.clone().with_env()?;
+    if cli.storage.is_some() {
+        config.storage = cli.storage;
+    }
+    if cli.registry.is_some() {
+        config.registry = cli.registry;
+    }
+    if cli.output.is_some() {
+        config.output = cli.output;
+    }
+    if cli.clipboard_timeout.is_some() {
+        config.clipboard_timeout = cli.clipboard_timeout;
+    }
+
+    if let CliCommand::Config { command } = cli.command {
+        match command {
+            ConfigCommand::Get { key } => {
+                let value = config.get(&key).ok_or(Error::NoSuchKey(key))?;
+                println!("{}", value);
+            },
+            ConfigCommand::Set { key, value } => {
+                let mut file_config = file_config;
+                file_config.set(&key, &value)?;
+                file_config.save(&config_path)?;
+            },
+            ConfigCommand::List {  } => {
+                for (key, value) in config.entries() {
+                    println!("{} = {}", key, value);
+                }
+            },
+        }
+        return Ok(());
+    }
+
+    let storage = if let Some(path) = config.storage.clone() {
         path
     } 
     else {
-        home::home_dir().unwrap().join(".enigmatic")
+        home::home_dir()
+            .ok_or_else(|| Error::InvalidInput(String::from("Home directory is unknown, set storage explicitly")))?
+            .join(".enigmatic")
     };
 
+    let catalog = RegistryCatalog::new(&storage);
+
+    // Resolved only by commands opening the selected registry, so a broken selection can still be fixed.
+    let registry_name = || config.registry
+        .clone()
+        .map(Ok)
+        .unwrap_or_else(|| catalog.default_registry());
+    let registry = || catalog.registry_path(&registry_name()?);
+
+    let identity = cli.identity
+        .map(|name| catalog.registry_path(&name))
+        .transpose()?;
+    let identity = identity.as_deref();
+
+    let verify = !cli.no_verify;
+    let lock_timeout = Duration::from_secs(cli.lock_timeout);
+    let output = config.output.unwrap_or(OutputFormat::Text);
+    let padding = config.padding()?;
+
     match cli.command {
-        CliCommand::Init { name, password } => {
+        CliCommand::Config { .. } => unreachable!(),
+        CliCommand::Init { name, init } => {
+            let registry = registry()?;
+
             let name = if let Some(name) = name {
                 name
             }
@@ -29,117 +104,945 @@ fn main() {
                 whoami::username()
             };
 
-            let password = ensure_new_password(password);
-            
-            RegistryRepository::init(&storage, &name, &password).unwrap();
+            init_registry(&registry, &name, init)?;
+        },
+        CliCommand::Registry { command } => match command {
+            RegistryCommand::Create { name, init } => {
+                let registry = catalog.registry_path(&name)?;
+
+                init_registry(&registry, &name, init)?;
+            },
+            RegistryCommand::List {  } => {
+                let default_registry = catalog.default_registry()?;
+
+                for name in catalog.list()? {
+                    let marker = if name == default_registry { '*' } else { ' ' };
+                    println!("{} {}", marker, name);
+                }
+            },
+            RegistryCommand::Use { name } => {
+                catalog.set_default_registry(&name)?;
+
+                if let Some(registry) = config.registry.as_ref().filter(|registry| **registry != name) {
+                    eprintln!("Registry [{}] is selected by configuration and takes precedence", registry);
+                }
+            },
+            RegistryCommand::Key { name } => {
+                let registry = match name {
+                    Some(name) => catalog.registry_path(&name)?,
+                    None => registry()?,
+                };
+
+                let registry_repository = RegistryRepository::open(&registry, LockMode::Shared, lock_timeout)?;
+                println!("{}", RegistryMember::format_public_key(registry_repository.public_key()));
+            },
+            RegistryCommand::Remove { name, yes } => {
+                let registry = catalog.registry_path(&name)?;
+
+                if !yes && !confirm(&format!("Remove registry [{}] with all its entries? Type its name to confirm: ", name), &name)? {
+                    println!("Registry was not removed");
+                    return Ok(());
+                }
+
+                let registry_repository = RegistryRepository::open(&registry, LockMode::Exclusive, lock_timeout)?;
+                registry_repository.remove()?;
+
+                if catalog.default_registry()? == name {
+                    catalog.reset_default_registry()?;
+                }
+            },
         },
-        CliCommand::Ls {  } => {
-            let registry_repository = RegistryRepository::open(&storage).unwrap();
-            let entry_service = EntryService::new(registry_repository).unwrap();
-            
-            println!("Registry [{}] content:", entry_service.registry_name());
-            for (name, entry) in entry_service.entries {
-                println!("{:32}: {}", name, entry.description)
+        CliCommand::Ls { all, at, password } => {
+            let registries = if all {
+                catalog.list()?
+                    .iter()
+                    .map(|name| catalog.registry_path(name))
+                    .collect::<Result<_, _>>()?
+            }
+            else {
+                vec![registry()?]
+            };
+
+            let mut listing = Vec::new();
+
+            for registry in registries {
+                let registry_repository = open_registry_log(&registry, password.clone(), LockMode::Shared, lock_timeout)?;
+                let entry_service = load_entries(registry_repository, verify, at)?;
+                
+                match output {
+                    OutputFormat::Text => {
+                        println!("Registry [{}] content:", entry_service.registry_name());
+                        for (name, entry) in entry_service.entries() {
+                            println!("{:32}: {}", name, entry.description)
+                        }
+                    },
+                    OutputFormat::Json => {
+                        for (name, entry) in entry_service.entries() {
+                            listing.push(json!({
+                                "registry": entry_service.registry_name(),
+                                "name": name,
+                                "description": entry.description,
+                                "timestamp": entry.timestamp as u64,
+                            }));
+                        }
+                    },
+                }
+            }
+
+            if output == OutputFormat::Json {
+                print_json(&listing)?;
             }
         },
         CliCommand::Copy { name, password } => {
-            let password = ensure_password(password);
+            let registry = registry()?;
 
-            let registry_repository = RegistryRepository::open_decrypt(&storage, &password).unwrap();
-            let entry_service = EntryService::new(registry_repository).unwrap();
+            let registry_repository = open_decrypt_registry(&registry, identity, password, LockMode::Shared, lock_timeout)?;
+            let entry_service = EntryService::new(registry_repository, verify)?;
 
-            let entry = entry_service.entries.get(&name);
+            let entry = entry_service.get(&name).ok_or_else(|| Error::NoSuchEntry(name.clone()))?;
+            let secret = decrypt_secret(&entry_service, &entry.secret)?;
 
-            if let Some(entry) = entry {
-                let secret = entry_service.decrypt_secret(&entry.secret);
-                if let Some(secret) = secret {
-                    let decoded = std::str::from_utf8(&secret).unwrap();
-                    let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-                    ctx.set_contents(String::from(decoded)).unwrap();
-                }
-                else {
-                    println!("Unable to decrypt secret");
-                }
+            drop(entry_service);
+            copy_to_clipboard(&secret, config.clipboard_timeout)?;
+        },
+        CliCommand::Show { name, password, copy, version, at } => {
+            let registry = registry()?;
+
+            let registry_repository = open_decrypt_registry(&registry, identity, password, LockMode::Shared, lock_timeout)?;
+            let mut entry_service = load_entries(registry_repository, verify, at)?;
+
+            let entry = if let Some(version) = version {
+                entry_service.history(&name)?
+                    .into_iter()
+                    .find(|revision| revision.version == version)
+                    .map(|revision| EntryModel {
+                        timestamp: revision.timestamp,
+                        secret_timestamp: revision.timestamp,
+                        description: revision.description,
+                        secret: revision.secret,
+                    })
             }
             else {
-                println!("Missing entry for given name");
+                entry_service.get(&name).cloned()
+            };
+
+            let entry = entry.ok_or_else(|| Error::NoSuchEntry(name.clone()))?;
+            let secret = decrypt_secret(&entry_service, &entry.secret)?;
+
+            match output {
+                OutputFormat::Text => {
+                    println!("{}: {}", name, entry.description);
+                    println!("{}", secret.as_str());
+                },
+                OutputFormat::Json => {
+                    let shown = json!({
+                        "name": name,
+                        "description": entry.description,
+                        "secret": secret.as_str(),
+                        "timestamp": entry.timestamp as u64,
+                    });
+                    print_json(&shown)?;
+                },
+            }
+
+            if copy {
+                drop(entry_service);
+                copy_to_clipboard(&secret, config.clipboard_timeout)?;
             }
         },
-        CliCommand::Show { name, password, copy } => {
-            let password = ensure_password(password);
+        CliCommand::History { name, password } => {
+            let registry = registry()?;
 
-            let registry_repository = RegistryRepository::open_decrypt(&storage, &password).unwrap();
-            let entry_service = EntryService::new(registry_repository).unwrap();
+            let registry_repository = open_registry_log(&registry, password, LockMode::Shared, lock_timeout)?;
+            let mut entry_service = EntryService::new(registry_repository, verify)?;
 
-            let entry = entry_service.entries.get(&name);
+            let history = entry_service.history(&name)?;
+
+            if history.is_empty() {
+                return Err(Error::NoSuchEntry(name));
+            }
 
-            if let Some(entry) = entry {
-                let secret = entry_service.decrypt_secret(&entry.secret);
-                if let Some(secret) = secret {
-                    let decoded = std::str::from_utf8(&secret).unwrap();
-                    if copy {
-                        let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-                        ctx.set_contents(String::from(decoded)).unwrap();
+            match output {
+                OutputFormat::Text => {
+                    println!("Entry [{}] history:", name);
+                    for revision in history {
+                        println!(
+                            "{:4} {} {:32}: {}", 
+                            revision.version, 
+                            format_timestamp(revision.timestamp), 
+                            revision.name, 
+                            describe_change(&revision.change),
+                        );
                     }
+                },
+                OutputFormat::Json => {
+                    let listing: Vec<_> = history.iter()
+                        .map(|revision| json!({
+                            "version": revision.version,
+                            "timestamp": revision.timestamp as u64,
+                            "name": revision.name,
+                            "change": describe_change(&revision.change),
+                            "description": revision.description,
+                        }))
+                        .collect();
+                    print_json(&listing)?;
+                },
+            }
+        },
+        CliCommand::Add { name, description, secret, generate, copy, password } => {
+            let registry = registry()?;
 
-                    println!("{}: {}", name, entry.description);
-                    println!("{}", decoded);
-                }
-                else {
-                    println!("Unable to decrypt secret");
+            let secret = match (secret, generate) {
+                (Some(secret), _) => Zeroizing::new(secret),
+                (None, Some(generate)) => enigmatic::generate(config.generator(&generate))?,
+                (None, None) => return Err(Error::InvalidInput(String::from("Either secret or generator is required"))),
+            };
+
+            let mut registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            registry_repository.set_padding(padding);
+            let mut entry_service = EntryService::new(registry_repository, verify)?;
+            entry_service.add(name, description.unwrap_or(String::new()), secret.as_bytes())?;
+            drop(entry_service);
+
+            if copy {
+                copy_to_clipboard(&secret, config.clipboard_timeout)?;
+            }
+        },
+        CliCommand::Set { name, new_name, description, secret, generate, copy, password } => {
+            let registry = registry()?;
+
+            let secret = match (secret, generate) {
+                (Some(secret), _) => Some(Zeroizing::new(secret)),
+                (None, Some(generate)) => Some(enigmatic::generate(config.generator(&generate))?),
+                (None, None) => None,
+            };
+
+            let mut registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            registry_repository.set_padding(padding);
+            let mut entry_service = EntryService::new(registry_repository, verify)?;
+            entry_service.set(name, new_name, description, secret.as_ref().map(|secret| secret.as_bytes()))?;
+            drop(entry_service);
+
+            if let Some(secret) = &secret {
+                if copy {
+                    copy_to_clipboard(secret, config.clipboard_timeout)?;
                 }
             }
+        },
+        CliCommand::Revert { name, to, password, .. } => {
+            let registry = registry()?;
+
+            let registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            let entry_service = EntryService::new(registry_repository, verify)?;
+            let registry_name = String::from(entry_service.registry_name());
+
+            let operations = entry_service.revert(name.as_deref(), to)?;
+
+            if let Some(name) = name {
+                println!("Entry [{}] reverted to {}: {} operations appended", name, format_timestamp(to), operations);
+            }
             else {
-                println!("Missing entry for given name");
+                println!("Registry [{}] reverted to {}: {} operations appended", registry_name, format_timestamp(to), operations);
             }
         },
-        CliCommand::Add { name, description, secret, generate, copy } => {
-            let secret = if let Some(secret) = secret {
-                secret
+        CliCommand::Del { name, password } => {
+            let registry = registry()?;
+
+            let registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            let mut entry_service = EntryService::new(registry_repository, verify)?;
+            entry_service.del(name)?;
+        },
+        CliCommand::Export { format, prefix, file, file_password, yes, password } => {
+            let registry = registry()?;
+            let format = ExchangeFormat::from(format);
+
+            if !format.exportable() {
+                return Err(Error::Unsupported(String::from("Format supports only import")));
+            }
+
+            let registry_repository = open_decrypt_registry(&registry, identity, password, LockMode::Shared, lock_timeout)?;
+            let entry_service = EntryService::new(registry_repository, verify)?;
+
+            let records = entry_service.export(prefix.as_deref())?;
+
+            if !yes {
+                let warning = format!(
+                    "WARNING: {} secrets of registry [{}] will be written UNENCRYPTED. Type export to confirm: ",
+                    records.len(),
+                    entry_service.registry_name(),
+                );
+                if !confirm(&warning, "export")? {
+                    return Err(Error::Declined(String::from("Nothing was exported")));
+                }
+            }
+
+            let file_password = format.encrypted().then(|| ensure_new_file_password(file_password)).transpose()?;
+
+            if let Some(file) = file {
+                format.write(create_private_file(&file)?, &records, file_password.as_ref().map(|password| password.as_str()))?;
             }
             else {
-                secrets::generate(&generate.unwrap())
+                format.write(io::stdout().lock(), &records, file_password.as_ref().map(|password| password.as_str()))?;
+            }
+        },
+        CliCommand::Import { file, format, on_conflict, dry_run, file_password, password } => {
+            let registry = registry()?;
+            let format = ExchangeFormat::from(format);
+
+            let file_password = format.encrypted().then(|| ensure_file_password(file_password)).transpose()?;
+            let content = format.read(File::open(file)?, file_password.as_ref().map(|password| password.as_str()))?;
+
+            let mut registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            registry_repository.set_padding(padding);
+            let mut entry_service = EntryService::new(registry_repository, verify)?;
+            let registry_name = String::from(entry_service.registry_name());
+
+            let mapping = format.field_mapping();
+            if !mapping.is_empty() {
+                println!("Field mapping:");
+                for (field, source) in mapping {
+                    println!("  {:12} <- {}", field, source);
+                }
+            }
+            if content.skipped > 0 {
+                println!("Items without login skipped: {}", content.skipped);
+            }
+
+            let report = entry_service.import(content.records, on_conflict.into(), dry_run)?;
+
+            print_import_report(&registry_name, &report, dry_run);
+        },
+        CliCommand::Sync { path, prefer, password } => {
+            let registry = registry()?;
+
+            if path.canonicalize().ok() == registry.canonicalize().ok() {
+                return Err(Error::InvalidInput(String::from("Can not synchronize registry with itself")));
+            }
+
+            let registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            let remote_repository = RegistryRepository::open(&path, LockMode::Exclusive, lock_timeout)?;
+            let entry_service = EntryService::new(registry_repository, verify)?;
+            let registry_name = String::from(entry_service.registry_name());
+
+            let report = entry_service.sync(remote_repository, |conflict| {
+                let side = match prefer {
+                    Some(SyncPreference::Local) => SyncSide::Local,
+                    Some(SyncPreference::Remote) => SyncSide::Remote,
+                    Some(SyncPreference::Newest) if conflict.remote_timestamp > conflict.local_timestamp => SyncSide::Remote,
+                    Some(SyncPreference::Newest) => SyncSide::Local,
+                    None => loop {
+                        let answer = prompt(&format!(
+                            "Entry [{}] changed on both sides (local {}, remote {}). Keep [l]ocal or [r]emote? ",
+                            conflict.name,
+                            format_timestamp(conflict.local_timestamp),
+                            format_timestamp(conflict.remote_timestamp),
+                        ))?;
+                        match answer.as_str() {
+                            "l" | "local" => break SyncSide::Local,
+                            "r" | "remote" => break SyncSide::Remote,
+                            _ => continue,
+                        }
+                    },
+                };
+
+                let kept = if side == SyncSide::Local { "local" } else { "remote" };
+                println!("Conflict on entry [{}] resolved with {} changes", conflict.name, kept);
+
+                Ok(side)
+            })?;
+
+            if report.local == 0 && report.remote == 0 {
+                println!("Registry [{}] is already in sync: {} operations", registry_name, report.common);
+                return Ok(());
+            }
+
+            println!(
+                "Registry [{}] synchronized: {} common, {} local and {} remote operations, {} merged",
+                registry_name,
+                report.common,
+                report.local,
+                report.remote,
+                report.merged,
+            );
+            if report.conflicts > 0 || report.skipped > 0 {
+                println!("Conflicts resolved: {}, operations skipped: {}", report.conflicts, report.skipped);
+            }
+        },
+        CliCommand::Backup { dest, keep } => {
+            let registry_name = registry_name()?;
+            let registry = catalog.registry_path(&registry_name)?;
+
+            let registry_repository = open_registry_log(&registry, None, LockMode::Shared, lock_timeout)?;
+            let mut entry_service = EntryService::new(registry_repository, verify)?;
+
+            let bundle = entry_service.backup()?;
+
+            let dest = dest.unwrap_or_else(|| storage.join("backups"));
+            let path = BackupBundle::path(&dest, &registry_name, bundle.created);
+            bundle.write(&path)?;
+
+            println!(
+                "Registry [{}] backed up to {}: {} entries, {} operations",
+                bundle.registry_name,
+                path.display(),
+                bundle.entries,
+                bundle.operations,
+            );
+
+            if let Some(keep) = keep {
+                let bundles = BackupBundle::list(&dest, &registry_name)?;
+                let expired = bundles.len().saturating_sub(keep as usize);
+
+                for path in &bundles[..expired] {
+                    fs::remove_file(path)?;
+                    println!("Removed expired backup {}", path.display());
+                }
+            }
+        },
+        CliCommand::Restore { bundle, yes, password } => {
+            let registry = registry()?;
+
+            let bundle = BackupBundle::read(&bundle)?;
+
+            println!(
+                "Backup of registry [{}] from {}: {} entries, {} operations",
+                bundle.registry_name,
+                format_timestamp(bundle.created),
+                bundle.entries,
+                bundle.operations,
+            );
+
+            let password = ensure_password(password)?;
+
+            let current = match RegistryRepository::open(&registry, LockMode::Exclusive, lock_timeout) {
+                Ok(mut registry_repository) => {
+                    let unlocked = if registry_repository.metadata_private() {
+                        registry_repository.unlock(&password)
+                    }
+                    else {
+                        Ok(())
+                    };
+
+                    Some(unlocked.and_then(|_| EntryService::new(registry_repository, verify)))
+                },
+                Err(Error::NoSuchRegistry(_)) => None,
+                Err(error) => Some(Err(error)),
             };
 
-            if copy {
-                let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-                ctx.set_contents(secret.clone()).unwrap();
+            let restored = EntryService::from_backup(&bundle, &registry, &password)?;
+
+            match &current {
+                Some(Ok(current)) => {
+                    let diff = current.diff(&restored);
+
+                    if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+                        restored.discard()?;
+                        println!("Registry entries already match backup");
+                        return Ok(());
+                    }
+
+                    for name in &diff.added {
+                        println!("+ {}", name);
+                    }
+                    for name in &diff.removed {
+                        println!("- {}", name);
+                    }
+                    for name in &diff.changed {
+                        println!("~ {}", name);
+                    }
+                    println!(
+                        "Restore will add {}, remove {} and change {} entries",
+                        diff.added.len(),
+                        diff.removed.len(),
+                        diff.changed.len(),
+                    );
+                },
+                Some(Err(error)) => println!("Current registry can not be read and will be replaced: {}", error),
+                None => println!("Registry does not exist and will be created"),
+            }
+
+            if !yes && !confirm("Type restore to replace registry: ", "restore")? {
+                restored.discard()?;
+                println!("Registry was not restored");
+                return Ok(());
+            }
+
+            if let Some(Ok(current)) = current {
+                if let Some(archive) = current.restore(restored)? {
+                    println!("Previous registry saved to {}", archive.display());
+                }
             }
+            else {
+                restored.install(&registry)?;
+            }
+
+            println!("Registry restored from backup");
+        },
+        CliCommand::Compact { archive, password } => {
+            let registry = registry()?;
 
-            let secret_bytes = secret.into_bytes();
+            let registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            let entry_service = EntryService::new(registry_repository, verify)?;
+            let registry_name = String::from(entry_service.registry_name());
 
-            let registry_repository = RegistryRepository::open(&storage).unwrap();
-            let mut entry_service = EntryService::new(registry_repository).unwrap();
-            entry_service.add(name, description.unwrap_or(String::new()), secret_bytes).unwrap();
+            let report = entry_service.compact(archive)?;
+
+            println!(
+                "Registry [{}] compacted: {} -> {} bytes, {} bytes reclaimed",
+                registry_name,
+                report.original_size,
+                report.compacted_size,
+                report.original_size.saturating_sub(report.compacted_size),
+            );
+            if let Some(archive) = report.archive {
+                println!("Previous log archived to {}", archive.display());
+            }
         },
-        CliCommand::Set { name, new_name, description, secret, generate, copy } => {
-            let secret = if let Some(secret) = secret {
-                Some(secret)
+        CliCommand::Repair { truncate_tail } => {
+            let registry = registry()?;
+
+            let mut registry_repository = open_registry_log(&registry, None, LockMode::Exclusive, lock_timeout)?;
+            if let Some(offset) = registry_repository.find_torn_tail()? {
+                if !truncate_tail {
+                    return Err(Error::CorruptLog {
+                        offset,
+                        reason: String::from("torn record, use --truncate-tail to drop it"),
+                    });
+                }
+
+                let dropped = registry_repository.truncate_tail()?;
+                println!("Truncated torn record at offset {}: {} bytes dropped", offset, dropped);
+            }
+            else {
+                println!("Registry log is intact");
             }
-            else if let Some(generate) = generate {
-                Some(secrets::generate(&generate))
+        },
+        CliCommand::Passwd { password, new_password, kdf_memory, kdf_iterations } => {
+            let registry = registry()?;
+
+            let password = ensure_password(password)?;
+
+            let registry_repository = RegistryRepository::open_decrypt(&registry, &password, LockMode::Exclusive, lock_timeout)?;
+            let registry_name = registry_repository.name.clone();
+
+            let new_password = ensure_changed_password(new_password)?;
+
+            let current = registry_repository.kdf();
+            let (default_memory, default_iterations) = if current.kdf_type == KDF_ARGON2ID {
+                (current.memory, current.iterations)
             }
             else {
-                None
+                (DEFAULT_KDF_MEMORY, DEFAULT_KDF_ITERATIONS)
             };
+            let kdf = KdfParameters::argon2id(
+                kdf_memory.unwrap_or(default_memory), 
+                kdf_iterations.unwrap_or(default_iterations),
+            );
 
-            if let Some(secret) = &secret {
-                if copy {
-                    let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-                    ctx.set_contents(secret.clone()).unwrap();
+            registry_repository.change_password(&new_password, kdf)?;
+
+            println!("Registry [{}] password changed", registry_name);
+        },
+        CliCommand::RotateKey { password, keep_history } => {
+            let registry = registry()?;
+
+            let password = ensure_password(password)?;
+
+            let mut registry_repository = RegistryRepository::open_decrypt(&registry, &password, LockMode::Exclusive, lock_timeout)?;
+            registry_repository.set_padding(padding);
+            let entry_service = EntryService::new(registry_repository, verify)?;
+            let registry_name = String::from(entry_service.registry_name());
+
+            let report = entry_service.rotate_key(&password, keep_history)?;
+
+            println!(
+                "Registry [{}] key rotated: {} entries re-encrypted in {} operations", 
+                registry_name, 
+                report.entries, 
+                report.operations,
+            );
+        },
+        CliCommand::Member { command } => match command {
+            MemberCommand::Add { public_key, name, password } => {
+                let registry = registry()?;
+
+                let public_key = RegistryMember::parse_public_key(&public_key)?;
+                let password = ensure_password(password)?;
+
+                let registry_repository = RegistryRepository::open_decrypt(&registry, &password, LockMode::Exclusive, lock_timeout)?;
+                let entry_service = EntryService::new(registry_repository, verify)?;
+                let registry_name = String::from(entry_service.registry_name());
+
+                let report = entry_service.add_member(&name, public_key)?;
+
+                println!(
+                    "Member [{}] added to registry [{}]: {} entries re-encrypted in {} operations",
+                    name,
+                    registry_name,
+                    report.entries,
+                    report.operations,
+                );
+            },
+            MemberCommand::List {  } => {
+                let registry = registry()?;
+
+                let registry_repository = RegistryRepository::open(&registry, LockMode::Shared, lock_timeout)?;
+                let entry_service = EntryService::new(registry_repository, verify)?;
+
+                match output {
+                    OutputFormat::Text => {
+                        println!("Registry [{}] members:", entry_service.registry_name());
+                        for member in entry_service.members() {
+                            let state = match member.removed {
+                                Some(removed) => format!("removed {}", format_timestamp(removed)),
+                                None => format!("added {}", format_timestamp(member.added)),
+                            };
+                            println!("{:32}: {} {}", member.name, RegistryMember::format_public_key(&member.public_key), state);
+                        }
+
+                        let pending = entry_service.rotation_pending();
+                        if !pending.is_empty() {
+                            println!("Secrets to rotate after member removal: {}", pending.join(", "));
+                        }
+                    },
+                    OutputFormat::Json => {
+                        let listing: Vec<_> = entry_service.members().iter()
+                            .map(|member| json!({
+                                "name": member.name,
+                                "public_key": RegistryMember::format_public_key(&member.public_key),
+                                "added": member.added as u64,
+                                "removed": member.removed.map(|removed| removed as u64),
+                                "rotate": member.rotate,
+                            }))
+                            .collect();
+                        print_json(&json!({
+                            "members": listing,
+                            "rotation_pending": entry_service.rotation_pending(),
+                        }))?;
+                    },
+                }
+            },
+            MemberCommand::Remove { name, rotate, password } => {
+                let registry = registry()?;
+
+                let password = ensure_password(password)?;
+
+                let registry_repository = RegistryRepository::open_decrypt(&registry, &password, LockMode::Exclusive, lock_timeout)?;
+                let entry_service = EntryService::new(registry_repository, verify)?;
+                let registry_name = String::from(entry_service.registry_name());
+
+                let report = entry_service.remove_member(&name, rotate)?;
+
+                println!(
+                    "Member [{}] removed from registry [{}]: {} entries re-encrypted in {} operations",
+                    name,
+                    registry_name,
+                    report.entries,
+                    report.operations,
+                );
+
+                if rotate {
+                    let registry_repository = RegistryRepository::open(&registry, LockMode::Shared, lock_timeout)?;
+                    let entry_service = EntryService::new(registry_repository, verify)?;
+
+                    let pending = entry_service.rotation_pending();
+                    if !pending.is_empty() {
+                        println!("Secrets to rotate: {}", pending.join(", "));
+                    }
                 }
+            },
+        },
+        CliCommand::Pad { password, keep_history, yes } => {
+            let registry = registry()?;
+
+            if !keep_history && !yes && !confirm("Previous entry versions will be dropped. Type pad to confirm: ", "pad")? {
+                return Err(Error::Declined(String::from("Nothing was padded, use --keep-history to keep previous entry versions")));
+            }
+
+            let password = ensure_password(password)?;
+
+            let mut registry_repository = RegistryRepository::open_decrypt(&registry, &password, LockMode::Exclusive, lock_timeout)?;
+            registry_repository.set_padding(padding.clone());
+            let entry_service = EntryService::new(registry_repository, verify)?;
+            let registry_name = String::from(entry_service.registry_name());
+
+            let report = entry_service.pad_secrets(keep_history)?;
+
+            println!(
+                "Registry [{}] secrets padded with {}: {} entries re-encrypted in {} operations", 
+                registry_name, 
+                padding, 
+                report.entries, 
+                report.operations,
+            );
+        },
+        CliCommand::Migrate { password } => {
+            let registry = registry()?;
+
+            let registry_repository = RegistryRepository::open(&registry, LockMode::Exclusive, lock_timeout)?;
+            let registry_name = registry_repository.name.clone();
+            let version = registry_repository.version();
+
+            if registry_repository.up_to_date() {
+                println!("Registry [{}] is up to date", registry_name);
+            }
+            else if let Some(backup) = registry_repository.migrate(&ensure_password(password)?)? {
+                println!("Registry [{}] migrated from version {}", registry_name, version);
+                println!("Previous registry saved to {}", backup.display());
+            }
+        },
+        CliCommand::Metadata { mode, password } => {
+            let registry = registry()?;
+
+            let registry_repository = open_registry(&registry, identity, password, LockMode::Exclusive, lock_timeout)?;
+            let registry_name = registry_repository.name.clone();
+
+            let private = mode == MetadataMode::Private;
+            let state = if private { "private" } else { "public" };
+
+            if let Some(backup) = registry_repository.convert_metadata(private)? {
+                println!("Registry [{}] metadata is now {}", registry_name, state);
+                println!("Previous registry saved to {}", backup.display());
+            }
+            else {
+                println!("Registry [{}] metadata is already {}", registry_name, state);
             }
+        },
+        CliCommand::Verify { password } => {
+            let registry = registry()?;
 
-            let secret_bytes = secret.map(|x| x.into_bytes());
+            let registry_repository = open_registry(&registry, identity, password, LockMode::Shared, lock_timeout)?;
+            let entry_service = EntryService::new(registry_repository, true)?;
 
-            let registry_repository = RegistryRepository::open(&storage).unwrap();
-            let mut entry_service = EntryService::new(registry_repository).unwrap();
-            entry_service.set(name, new_name, description, secret_bytes).unwrap();
+            if let Some(offset) = entry_service.torn_tail() {
+                return Err(Error::CorruptLog {
+                    offset,
+                    reason: String::from("torn record"),
+                });
+            }
+
+            println!(
+                "Registry [{}] is valid: {} operations, {} entries", 
+                entry_service.registry_name(), 
+                entry_service.operations(),
+                entry_service.entries().len(),
+            );
+            if let Some(authenticated) = entry_service.authenticated_operations() {
+                println!("Authenticated operations: {}", authenticated);
+            }
+            for (index, offset) in entry_service.unauthenticated_operations() {
+                println!("Unauthenticated operation {} at offset {}", index, offset);
+            }
         },
-        CliCommand::Del { name } => {
-            let registry_repository = RegistryRepository::open(&storage).unwrap();
-            let mut entry_service = EntryService::new(registry_repository).unwrap();
-            entry_service.del(name).unwrap();
+    }
+
+    Ok(())
+}
+
+fn init_registry(registry: &Path, name: &str, init: InitArgs) -> Result<(), Error> {
+    let password = ensure_new_password(init.password)?;
+    
+    let kdf = KdfParameters::argon2id(init.kdf_memory, init.kdf_iterations);
+
+    let storage: Box<dyn RegistryStorage> = match init.backend {
+        StorageBackend::File => Box::new(FileStorage::create(registry)?),
+        StorageBackend::Sqlite => Box::new(SqliteStorage::create(registry)?),
+    };
+
+    RegistryRepository::init_with(storage, name, &password, init.authenticated, init.private_metadata, kdf)?;
+
+    Ok(())
+}
+
+fn open_registry(
+    registry: &Path, 
+    identity: Option<&Path>, 
+    password: Option<String>, 
+    lock_mode: LockMode, 
+    lock_timeout: Duration,
+) -> Result<RegistryRepository, Error> {
+    let mut registry_repository = RegistryRepository::open(registry, lock_mode, lock_timeout)?;
+
+    if let Some(identity) = identity.filter(|identity| *identity != registry) {
+        unlock_member(&mut registry_repository, identity, password, lock_timeout)?;
+    }
+    else if password.is_some() || registry_repository.authentication_required() || registry_repository.metadata_private() {
+        registry_repository.unlock(&ensure_password(password)?)?;
+    }
+
+    Ok(registry_repository)
+}
+
+/// Opens registry unlocked with its password, or as a team member with the personal `identity` registry.
+fn open_decrypt_registry(
+    registry: &Path, 
+    identity: Option<&Path>, 
+    password: Option<String>, 
+    lock_mode: LockMode, 
+    lock_timeout: Duration,
+) -> Result<RegistryRepository, Error> {
+    let Some(identity) = identity.filter(|identity| *identity != registry) else {
+        return RegistryRepository::open_decrypt(registry, &ensure_password(password)?, lock_mode, lock_timeout);
+    };
+
+    let mut registry_repository = RegistryRepository::open(registry, lock_mode, lock_timeout)?;
+    unlock_member(&mut registry_repository, identity, password, lock_timeout)?;
+
+    Ok(registry_repository)
+}
+
+fn unlock_member(registry_repository: &mut RegistryRepository, identity: &Path, password: Option<String>, lock_timeout: Duration) -> Result<(), Error> {
+    let identity = RegistryRepository::open_decrypt(identity, &ensure_password(password)?, LockMode::Shared, lock_timeout)?;
+
+    registry_repository.unlock_as(&identity)
+}
+
+/// Opens registry for commands reading its log without needing the private key,
+/// asking for the password only when entry names and descriptions are private.
+fn open_registry_log(registry: &Path, password: Option<String>, lock_mode: LockMode, lock_timeout: Duration) -> Result<RegistryRepository, Error> {
+    let mut registry_repository = RegistryRepository::open(registry, lock_mode, lock_timeout)?;
+
+    if password.is_some() || registry_repository.metadata_private() {
+        registry_repository.unlock(&ensure_password(password)?)?;
+    }
+
+    Ok(registry_repository)
+}
+
+fn format_timestamp(timestamp: u128) -> String {
+    Local.timestamp_millis_opt(timestamp as i64)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn describe_change(change: &EntryChange) -> String {
+    match change {
+        EntryChange::Added => String::from("added"),
+        EntryChange::Changed { renamed_from, description, secret } => {
+            let mut changes = Vec::new();
+            if let Some(renamed_from) = renamed_from {
+                changes.push(format!("renamed from {}", renamed_from));
+            }
+            if *description {
+                changes.push(String::from("description changed"));
+            }
+            if *secret {
+                changes.push(String::from("secret changed"));
+            }
+            if changes.is_empty() {
+                changes.push(String::from("touched"));
+            }
+            changes.join(", ")
         },
+        EntryChange::Deleted => String::from("deleted"),
+    }
+}
+
+fn decrypt_secret(entry_service: &EntryService, secret: &[u8]) -> Result<Zeroizing<String>, Error> {
+    entry_service.decrypt_secret(secret)
+        .and_then(|secret| str::from_utf8(&secret).ok().map(|secret| Zeroizing::new(String::from(secret))))
+        .ok_or_else(|| Error::InvalidData(String::from("Unable to decrypt secret")))
+}
+
+/// Copies `secret` and waits `timeout` seconds to clear it. Callers release the registry lock first.
+fn copy_to_clipboard(secret: &str, timeout: Option<u64>) -> Result<(), Error> {
+    let mut ctx: ClipboardContext = ClipboardProvider::new().map_err(clipboard_error)?;
+    ctx.set_contents(String::from(secret)).map_err(clipboard_error)?;
+
+    if let Some(timeout) = timeout.filter(|timeout| *timeout > 0) {
+        println!("Clipboard will be cleared in {} seconds", timeout);
+        thread::sleep(Duration::from_secs(timeout));
+
+        if ctx.get_contents().map(|contents| contents == secret).unwrap_or(false) {
+            ctx.set_contents(String::new()).map_err(clipboard_error)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates or truncates `path` readable by the owner only, for files holding plaintext secrets.
+fn create_private_file(path: &Path) -> Result<File, Error> {
+    let mut options = File::options();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        options.mode(0o600);
+        let file = options.open(path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+
+        Ok(file)
     }
-}
\ No newline at end of file
+
+    #[cfg(not(unix))]
+    Ok(options.open(path)?)
+}
+
+fn clipboard_error(error: Box<dyn std::error::Error>) -> Error {
+    Error::Io(io::Error::other(format!("Clipboard is not available: {}", error)))
+}
+
+fn load_entries(registry_repository: RegistryRepository, verify: bool, at: Option<u128>) -> Result<EntryService, Error> {
+    if let Some(at) = at {
+        EntryService::at(registry_repository, verify, at)
+    }
+    else {
+        EntryService::new(registry_repository, verify)
+    }
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(value).map_err(io::Error::from)?);
+    Ok(())
+}
+
+fn print_import_report(registry_name: &str, report: &ImportReport, dry_run: bool) {
+    if dry_run {
+        for name in &report.added {
+            println!("add       {}", name);
+        }
+        for name in &report.overwritten {
+            println!("overwrite {}", name);
+        }
+        for (name, new_name) in &report.renamed {
+            println!("rename    {} -> {}", name, new_name);
+        }
+        for name in &report.skipped {
+            println!("skip      {}", name);
+        }
+    }
+
+    println!(
+        "Registry [{}] {}: {} added, {} overwritten, {} renamed, {} skipped",
+        registry_name,
+        if dry_run { "import dry run" } else { "import" },
+        report.added.len(),
+        report.overwritten.len(),
+        report.renamed.len(),
+        report.skipped.len(),
+    );
+}
+
+fn confirm(prompt_text: &str, expected: &str) -> Result<bool, io::Error> {
+    match prompt(prompt_text) {
+        Ok(answer) => Ok(answer == expected),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+fn prompt(prompt: &str) -> Result<String, io::Error> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(String::from(answer.trim()))
+}
@@ -0,0 +1,74 @@
+use std::{env, io::{self, Write}};
+
+use rpassword::read_password;
+use zeroize::Zeroizing;
+
+pub fn ensure_password(password: Option<String>) -> Result<Zeroizing<String>, io::Error> {
+    if let Some(password) = password {
+        return Ok(Zeroizing::new(password));
+    }
+
+    if let Ok(password) = env::var("ENIGMATIC_PASSWORD") {
+        return Ok(Zeroizing::new(password));
+    }
+
+    print!("Password: ");
+    io::stdout().flush()?;
+    read_password().map(Zeroizing::new)
+}
+
+pub fn ensure_new_password(password: Option<String>) -> Result<Zeroizing<String>, io::Error> {
+    if let Some(password) = password {
+        return Ok(Zeroizing::new(password));
+    }
+    
+    if let Ok(password) = env::var("ENIGMATIC_PASSWORD") {
+        return Ok(Zeroizing::new(password));
+    }
+
+    read_new_password("Password: ")
+}
+
+pub fn ensure_changed_password(password: Option<String>) -> Result<Zeroizing<String>, io::Error> {
+    if let Some(password) = password {
+        return Ok(Zeroizing::new(password));
+    }
+
+    read_new_password("New password: ")
+}
+
+pub fn ensure_file_password(password: Option<String>) -> Result<Zeroizing<String>, io::Error> {
+    if let Some(password) = password {
+        return Ok(Zeroizing::new(password));
+    }
+
+    print!("File password: ");
+    io::stdout().flush()?;
+    read_password().map(Zeroizing::new)
+}
+
+pub fn ensure_new_file_password(password: Option<String>) -> Result<Zeroizing<String>, io::Error> {
+    if let Some(password) = password {
+        return Ok(Zeroizing::new(password));
+    }
+
+    read_new_password("File password: ")
+}
+
+fn read_new_password(prompt: &str) -> Result<Zeroizing<String>, io::Error> {
+    loop {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let password = Zeroizing::new(read_password()?);
+
+        print!("Repeat: ");
+        io::stdout().flush()?;
+        let repeated = Zeroizing::new(read_password()?);
+
+        if password == repeated {
+            return Ok(password);
+        }
+
+        println!("Passwords are not equal");
+    }
+}
\ No newline at end of file
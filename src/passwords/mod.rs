@@ -0,0 +1,7 @@
+mod ensure_password;
+
+pub use ensure_password::ensure_password;
+pub use ensure_password::ensure_new_password;
+pub use ensure_password::ensure_changed_password;
+pub use ensure_password::ensure_file_password;
+pub use ensure_password::ensure_new_file_password;
\ No newline at end of file
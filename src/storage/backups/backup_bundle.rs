@@ -0,0 +1,159 @@
+use std::{fs::{self, File}, io::{self, Read, Write}, path::{Path, PathBuf}};
+
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::Error;
+
+/// Self-describing registry snapshot: metadata, raw registry header region 
+/// and log encrypted with registry public key, followed by SHA3-256 checksum
+pub struct BackupBundle {
+    pub created: u128,
+    pub registry_name: String,
+    pub entries: u64,
+    pub operations: u64,
+    pub last_hash: [u8; 64],
+    pub header_region: Vec<u8>,
+    pub encrypted_log: Vec<u8>,
+}
+
+const BUNDLE_MAGIC: &[u8; 8] = b"ENIGBNDL";
+const BUNDLE_VERSION: i32 = 1;
+const BUNDLE_EXTENSION: &str = "bundle";
+
+impl BackupBundle {
+    pub fn path(dir: &Path, registry: &str, created: u128) -> PathBuf {
+        dir.join(format!("{}.{}.{}", registry, created, BUNDLE_EXTENSION))
+    }
+
+    /// Lists bundles of registry in directory from oldest to newest
+    pub fn list(dir: &Path, registry: &str) -> Result<Vec<PathBuf>, Error> {
+        let mut result = Vec::new();
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            let created = path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(registry))
+                .and_then(|name| name.strip_prefix('.'))
+                .and_then(|name| name.strip_suffix(BUNDLE_EXTENSION))
+                .and_then(|name| name.strip_suffix('.'))
+                .and_then(|created| created.parse::<u128>().ok());
+
+            if let Some(created) = created {
+                result.push((created, path));
+            }
+        }
+
+        result.sort();
+
+        Ok(result.into_iter().map(|(_, path)| path).collect())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let content = fs::read(path)?;
+
+        if content.len() < BUNDLE_MAGIC.len() + 32 || !content.starts_with(BUNDLE_MAGIC) {
+            return Err(invalid_data("File is not a registry backup bundle"));
+        }
+
+        let (body, checksum) = content.split_at(content.len() - 32);
+        if Sha3_256::digest(body).as_slice() != checksum {
+            return Err(invalid_data("Backup bundle checksum mismatch"));
+        }
+
+        let mut reader = &body[BUNDLE_MAGIC.len()..];
+
+        let version = i32::from_le_bytes(read_array(&mut reader)?);
+        if version != BUNDLE_VERSION {
+            return Err(Error::UnsupportedVersion(version as i64));
+        }
+
+        let created = u128::from_le_bytes(read_array(&mut reader)?);
+        let registry_name = String::from_utf8(read_block(&mut reader)?)
+            .map_err(|_| invalid_data("Backup bundle registry name is malformed"))?;
+        let entries = u64::from_le_bytes(read_array(&mut reader)?);
+        let operations = u64::from_le_bytes(read_array(&mut reader)?);
+        let last_hash = read_array(&mut reader)?;
+        let header_region = read_block(&mut reader)?;
+        let encrypted_log = read_block(&mut reader)?;
+
+        if !reader.is_empty() {
+            return Err(invalid_data("Backup bundle has trailing data"));
+        }
+
+        let result = Self {
+            created,
+            registry_name,
+            entries,
+            operations,
+            last_hash,
+            header_region,
+            encrypted_log,
+        };
+
+        Ok(result)
+    }
+
+    /// Writes bundle next to destination and renames it into place
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let mut body = Vec::new();
+        body.extend_from_slice(BUNDLE_MAGIC);
+        body.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+        body.extend_from_slice(&self.created.to_le_bytes());
+        write_block(&mut body, self.registry_name.as_bytes());
+        body.extend_from_slice(&self.entries.to_le_bytes());
+        body.extend_from_slice(&self.operations.to_le_bytes());
+        body.extend_from_slice(&self.last_hash);
+        write_block(&mut body, &self.header_region);
+        write_block(&mut body, &self.encrypted_log);
+
+        let checksum = Sha3_256::digest(&body);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&body)?;
+        file.write_all(&checksum)?;
+        file.sync_all()?;
+
+        fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], Error> {
+    let mut result = [0u8; N];
+    reader.read_exact(&mut result).map_err(|_| invalid_data("Backup bundle is truncated"))?;
+    Ok(result)
+}
+
+fn read_block(reader: &mut impl Read) -> Result<Vec<u8>, Error> {
+    let size = u64::from_le_bytes(read_array(reader)?);
+    let mut result = Vec::new();
+
+    reader.by_ref().take(size).read_to_end(&mut result)?;
+    if result.len() as u64 != size {
+        return Err(invalid_data("Backup bundle is truncated"));
+    }
+
+    Ok(result)
+}
+
+fn write_block(writer: &mut Vec<u8>, data: &[u8]) {
+    writer.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    writer.extend_from_slice(data);
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::InvalidData(String::from(message))
+}
@@ -0,0 +1,3 @@
+mod backup_bundle;
+
+pub use backup_bundle::BackupBundle;
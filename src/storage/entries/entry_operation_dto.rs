@@ -1,3 +1,8 @@
+/// Entry operation as read from and appended to a [`RegistryStorage`](crate::RegistryStorage).
+///
+/// `hash` holds the hash chaining the operation to the previous one followed by its authentication tag.
+/// Secrets and, in registries with private metadata, names and descriptions are stored encrypted.
+#[derive(Clone)]
 pub enum EntryOperationDto {
     Add {
         hash: [u8; 64],
@@ -21,4 +26,30 @@ pub enum EntryOperationDto {
         timestamp: u128,
         name: String,
     },
+}
+
+impl EntryOperationDto {
+    pub fn hash(&self) -> &[u8; 64] {
+        match self {
+            EntryOperationDto::Add { hash, .. } => hash,
+            EntryOperationDto::Set { hash, .. } => hash,
+            EntryOperationDto::Del { hash, .. } => hash,
+        }
+    }
+
+    pub fn set_hash(&mut self, value: [u8; 64]) {
+        match self {
+            EntryOperationDto::Add { hash, .. } => *hash = value,
+            EntryOperationDto::Set { hash, .. } => *hash = value,
+            EntryOperationDto::Del { hash, .. } => *hash = value,
+        }
+    }
+
+    pub fn timestamp(&self) -> u128 {
+        match self {
+            EntryOperationDto::Add { timestamp, .. } => *timestamp,
+            EntryOperationDto::Set { timestamp, .. } => *timestamp,
+            EntryOperationDto::Del { timestamp, .. } => *timestamp,
+        }
+    }
 }
\ No newline at end of file
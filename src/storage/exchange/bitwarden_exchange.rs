@@ -0,0 +1,97 @@
+use std::{collections::HashMap, io::Read};
+
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+use super::{ExchangeContent, LoginRecord};
+
+const LOGIN_ITEM_TYPE: i32 = 1;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<BitwardenFolder>,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenFolder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BitwardenItem {
+    #[serde(rename = "type")]
+    item_type: i32,
+    name: Option<String>,
+    notes: Option<String>,
+    folder_id: Option<String>,
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    password: Option<String>,
+    #[serde(default)]
+    uris: Option<Vec<BitwardenUri>>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenUri {
+    uri: Option<String>,
+}
+
+/// Reads unencrypted Bitwarden JSON export, only login items are taken
+pub fn read(reader: impl Read) -> Result<ExchangeContent, Error> {
+    let export: BitwardenExport = serde_json::from_reader(reader)
+        .map_err(|error| Error::InvalidData(error.to_string()))?;
+
+    if export.encrypted {
+        return Err(Error::Unsupported(
+            String::from("Encrypted Bitwarden exports are not supported, export unencrypted JSON"),
+        ));
+    }
+
+    let folders: HashMap<_, _> = export.folders
+        .into_iter()
+        .map(|folder| (folder.id, folder.name))
+        .collect();
+
+    let mut result = ExchangeContent::from(Vec::new());
+
+    for item in export.items {
+        let login = match item.login {
+            Some(login) if item.item_type == LOGIN_ITEM_TYPE => login,
+            _ => {
+                result.skipped += 1;
+                continue;
+            },
+        };
+
+        let login = LoginRecord {
+            folder: item.folder_id.and_then(|id| folders.get(&id).cloned()),
+            title: item.name.unwrap_or_default(),
+            url: login.uris
+                .unwrap_or_default()
+                .into_iter()
+                .find_map(|uri| uri.uri)
+                .unwrap_or_default(),
+            username: login.username.unwrap_or_default(),
+            password: Zeroizing::new(login.password.unwrap_or_default()),
+            notes: item.notes.unwrap_or_default(),
+        };
+
+        result.records.push(login.into_record());
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,47 @@
+use std::io::Read;
+
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+use super::{ExchangeContent, LoginRecord, csv_exchange};
+
+/// Reads Chrome password CSV: name, url, username, password, note
+pub fn read_chrome(reader: impl Read) -> Result<ExchangeContent, Error> {
+    let mut result = ExchangeContent::from(Vec::new());
+
+    for mut row in csv_exchange::read_table(reader)? {
+        let login = LoginRecord {
+            folder: None,
+            title: csv_exchange::column(&mut row, &["name"]),
+            url: csv_exchange::column(&mut row, &["url"]),
+            username: csv_exchange::column(&mut row, &["username"]),
+            password: Zeroizing::new(csv_exchange::column(&mut row, &["password"])),
+            notes: csv_exchange::column(&mut row, &["note"]),
+        };
+
+        result.records.push(login.into_record());
+    }
+
+    Ok(result)
+}
+
+/// Reads Firefox logins CSV, entries are named after url host
+pub fn read_firefox(reader: impl Read) -> Result<ExchangeContent, Error> {
+    let mut result = ExchangeContent::from(Vec::new());
+
+    for mut row in csv_exchange::read_table(reader)? {
+        let login = LoginRecord {
+            folder: None,
+            title: String::new(),
+            url: csv_exchange::column(&mut row, &["url"]),
+            username: csv_exchange::column(&mut row, &["username"]),
+            password: Zeroizing::new(csv_exchange::column(&mut row, &["password"])),
+            notes: String::new(),
+        };
+
+        result.records.push(login.into_record());
+    }
+
+    Ok(result)
+}
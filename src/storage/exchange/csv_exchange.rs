@@ -0,0 +1,62 @@
+use std::{collections::HashMap, io::{self, Read, Write}};
+
+use crate::errors::Error;
+
+use super::ExchangeRecord;
+
+pub fn read(reader: impl Read) -> Result<Vec<ExchangeRecord>, Error> {
+    csv::Reader::from_reader(reader)
+        .deserialize()
+        .collect::<Result<_, _>>()
+        .map_err(invalid_data)
+}
+
+pub fn write(writer: impl Write, records: &[ExchangeRecord]) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    for record in records {
+        writer.serialize(record).map_err(io::Error::from)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Reads rows keyed by lowercase header names
+pub fn read_table(reader: impl Read) -> Result<Vec<HashMap<String, String>>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers: Vec<_> = reader.headers()
+        .map_err(invalid_data)?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+
+    let mut result = Vec::new();
+
+    for row in reader.records() {
+        let row = row.map_err(invalid_data)?;
+        let row = headers.iter()
+            .cloned()
+            .zip(row.iter().map(String::from))
+            .collect();
+
+        result.push(row);
+    }
+
+    Ok(result)
+}
+
+/// Takes first present column of given names
+pub fn column(row: &mut HashMap<String, String>, names: &[&str]) -> String {
+    names.iter()
+        .find_map(|name| row.remove(*name))
+        .unwrap_or_default()
+}
+
+fn invalid_data(error: csv::Error) -> Error {
+    Error::InvalidData(error.to_string())
+}
@@ -0,0 +1,123 @@
+use std::{collections::HashMap, io::{BufRead, BufReader, Read, Write}};
+
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+use super::ExchangeRecord;
+
+/// Reads `NAME=value` lines, a comment right above assignment becomes its description.
+/// Entries are named after the variables, the names reduced on export are not restored.
+pub fn read(reader: impl Read) -> Result<Vec<ExchangeRecord>, Error> {
+    let mut result = Vec::new();
+    let mut description = String::new();
+
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            description.clear();
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            description = String::from(comment.trim());
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line.split_once('=').ok_or_else(|| Error::InvalidData(
+            format!("Invalid dotenv assignment at line {}", index + 1),
+        ))?;
+
+        let record = ExchangeRecord {
+            name: String::from(name.trim()),
+            description: std::mem::take(&mut description),
+            secret: Zeroizing::new(unquote(value.trim())),
+        };
+
+        result.push(record);
+    }
+
+    Ok(result)
+}
+
+/// Writes entries as `NAME="value"` lines, names are reduced to variable safe characters.
+/// Fails before writing anything when two entries reduce to the same variable.
+pub fn write(mut writer: impl Write, records: &[ExchangeRecord]) -> Result<(), Error> {
+    let mut variables = HashMap::new();
+    for record in records {
+        if let Some(other) = variables.insert(variable_name(&record.name), &record.name) {
+            return Err(Error::InvalidInput(format!(
+                "Entries {} and {} both export as dotenv variable {}",
+                other,
+                record.name,
+                variable_name(&record.name),
+            )));
+        }
+    }
+
+    for record in records {
+        if !record.description.is_empty() {
+            writeln!(writer, "# {}", record.description.replace('\n', " "))?;
+        }
+
+        writeln!(writer, "{}={}", variable_name(&record.name), quote(&record.secret))?;
+    }
+
+    Ok(())
+}
+
+fn variable_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn quote(value: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '$' => result.push_str("\\$"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+fn unquote(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+        return String::from(inner);
+    }
+
+    let Some(inner) = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) else {
+        return String::from(value);
+    };
+
+    let mut result = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
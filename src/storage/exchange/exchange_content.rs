@@ -0,0 +1,15 @@
+use super::ExchangeRecord;
+
+pub struct ExchangeContent {
+    pub records: Vec<ExchangeRecord>,
+    pub skipped: usize,
+}
+
+impl From<Vec<ExchangeRecord>> for ExchangeContent {
+    fn from(records: Vec<ExchangeRecord>) -> Self {
+        Self {
+            records,
+            skipped: 0,
+        }
+    }
+}
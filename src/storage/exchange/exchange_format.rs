@@ -0,0 +1,91 @@
+use std::io::{Read, Write};
+
+use crate::errors::Error;
+
+use super::{
+    ExchangeContent, 
+    ExchangeRecord, 
+    bitwarden_exchange, 
+    browser_exchange, 
+    csv_exchange, 
+    dotenv_exchange, 
+    json_exchange, 
+    kdbx_exchange, 
+    one_password_exchange,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeFormat {
+    Json,
+    Csv,
+    Dotenv,
+    Kdbx,
+    Bitwarden,
+    OnePassword,
+    ChromeCsv,
+    FirefoxCsv,
+}
+
+impl ExchangeFormat {
+    pub fn encrypted(&self) -> bool {
+        *self == ExchangeFormat::Kdbx
+    }
+
+    pub fn exportable(&self) -> bool {
+        matches!(self, ExchangeFormat::Json | ExchangeFormat::Csv | ExchangeFormat::Dotenv | ExchangeFormat::Kdbx)
+    }
+
+    /// Describes where imported entry fields come from in formats of other password managers
+    pub fn field_mapping(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            ExchangeFormat::Bitwarden => &[
+                ("name", "folder/name, login items only"),
+                ("secret", "login.password"),
+                ("description", "login.username, first login.uris entry and notes"),
+            ],
+            ExchangeFormat::OnePassword => &[
+                ("name", "vault/title for 1PUX, title for CSV, login and password items only"),
+                ("secret", "password field"),
+                ("description", "username field, url and notes"),
+            ],
+            ExchangeFormat::ChromeCsv => &[
+                ("name", "name, url host when name is empty"),
+                ("secret", "password"),
+                ("description", "username, url and note"),
+            ],
+            ExchangeFormat::FirefoxCsv => &[
+                ("name", "url host"),
+                ("secret", "password"),
+                ("description", "username and url"),
+            ],
+            _ => &[],
+        }
+    }
+
+    pub fn read(&self, reader: impl Read, password: Option<&str>) -> Result<ExchangeContent, Error> {
+        match self {
+            ExchangeFormat::Json => json_exchange::read(reader).map(ExchangeContent::from),
+            ExchangeFormat::Csv => csv_exchange::read(reader).map(ExchangeContent::from),
+            ExchangeFormat::Dotenv => dotenv_exchange::read(reader).map(ExchangeContent::from),
+            ExchangeFormat::Kdbx => kdbx_exchange::read(reader, password.ok_or_else(missing_password)?).map(ExchangeContent::from),
+            ExchangeFormat::Bitwarden => bitwarden_exchange::read(reader),
+            ExchangeFormat::OnePassword => one_password_exchange::read(reader),
+            ExchangeFormat::ChromeCsv => browser_exchange::read_chrome(reader),
+            ExchangeFormat::FirefoxCsv => browser_exchange::read_firefox(reader),
+        }
+    }
+
+    pub fn write(&self, writer: impl Write, records: &[ExchangeRecord], password: Option<&str>) -> Result<(), Error> {
+        match self {
+            ExchangeFormat::Json => json_exchange::write(writer, records),
+            ExchangeFormat::Csv => csv_exchange::write(writer, records),
+            ExchangeFormat::Dotenv => dotenv_exchange::write(writer, records),
+            ExchangeFormat::Kdbx => kdbx_exchange::write(writer, records, password.ok_or_else(missing_password)?),
+            _ => Err(Error::Unsupported(String::from("Format supports only import"))),
+        }
+    }
+}
+
+fn missing_password() -> Error {
+    Error::PasswordRequired(String::from("Encrypted format requires file password"))
+}
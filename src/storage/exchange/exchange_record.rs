@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+#[derive(Serialize, Deserialize)]
+pub struct ExchangeRecord {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub secret: Zeroizing<String>,
+}
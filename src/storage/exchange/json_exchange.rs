@@ -0,0 +1,16 @@
+use std::io::{self, Read, Write};
+
+use crate::errors::Error;
+
+use super::ExchangeRecord;
+
+pub fn read(reader: impl Read) -> Result<Vec<ExchangeRecord>, Error> {
+    serde_json::from_reader(reader).map_err(|error| Error::InvalidData(error.to_string()))
+}
+
+pub fn write(mut writer: impl Write, records: &[ExchangeRecord]) -> Result<(), Error> {
+    serde_json::to_writer_pretty(&mut writer, records).map_err(io::Error::from)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
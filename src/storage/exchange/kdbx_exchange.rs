@@ -0,0 +1,118 @@
+use std::io::{Read, Write};
+
+use keepass::{
+    error::{DatabaseKeyError, DatabaseOpenError},
+    config::{DatabaseConfig, KdfConfig, OuterCipherConfig}, 
+    db::{Entry, Group, Node, Value}, 
+    Database, 
+    DatabaseKey,
+};
+use secstr::SecStr;
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+use super::ExchangeRecord;
+
+const NAME_SEPARATOR: char = '/';
+
+/// Reads KDBX 4 database, groups below root become name prefixes and notes become descriptions
+pub fn read(mut reader: impl Read, password: &str) -> Result<Vec<ExchangeRecord>, Error> {
+    let database = Database::open(&mut reader, DatabaseKey::new().with_password(password))
+        .map_err(|error| match error {
+            DatabaseOpenError::Key(DatabaseKeyError::IncorrectKey) => Error::WrongPassword,
+            DatabaseOpenError::Io(error) => Error::Io(error),
+            error => Error::InvalidData(error.to_string()),
+        })?;
+
+    let mut result = Vec::new();
+    read_group(&database, &database.root, "", &mut result);
+
+    Ok(result)
+}
+
+/// Writes KDBX 4 database with Argon2id and ChaCha20
+pub fn write(mut writer: impl Write, records: &[ExchangeRecord], password: &str) -> Result<(), Error> {
+    let config = DatabaseConfig {
+        outer_cipher_config: OuterCipherConfig::ChaCha20,
+        kdf_config: KdfConfig::Argon2id {
+            iterations: 3,
+            memory: 64 * 1024 * 1024,
+            parallelism: 1,
+            version: rust_argon2::Version::Version13,
+        },
+        ..DatabaseConfig::default()
+    };
+
+    let mut database = Database::new(config);
+
+    for record in records {
+        let mut path: Vec<_> = record.name
+            .split(NAME_SEPARATOR)
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let title = path.pop().unwrap_or(&record.name);
+
+        let mut entry = Entry::new();
+        entry.fields.insert(String::from("Title"), Value::Unprotected(String::from(title)));
+        entry.fields.insert(String::from("Password"), Value::Protected(SecStr::from(record.secret.as_str())));
+        if !record.description.is_empty() {
+            entry.fields.insert(String::from("Notes"), Value::Unprotected(record.description.clone()));
+        }
+
+        ensure_group(&mut database.root, &path).add_child(entry);
+    }
+
+    database.save(&mut writer, DatabaseKey::new().with_password(password))
+        .map_err(|error| Error::InvalidInput(error.to_string()))
+}
+
+fn read_group(database: &Database, group: &Group, path: &str, result: &mut Vec<ExchangeRecord>) {
+    for node in &group.children {
+        match node {
+            Node::Group(child) if Some(child.uuid) == database.meta.recyclebin_uuid => {},
+            Node::Group(child) => read_group(database, child, &join_name(path, &child.name), result),
+            Node::Entry(entry) => {
+                let title = entry.get_title()
+                    .map(String::from)
+                    .unwrap_or_else(|| entry.get_uuid().to_string());
+
+                let record = ExchangeRecord {
+                    name: join_name(path, &title),
+                    description: String::from(entry.get("Notes").unwrap_or_default()),
+                    secret: Zeroizing::new(String::from(entry.get_password().unwrap_or_default())),
+                };
+
+                result.push(record);
+            },
+        }
+    }
+}
+
+fn ensure_group<'a>(group: &'a mut Group, path: &[&str]) -> &'a mut Group {
+    let Some((name, path)) = path.split_first() else {
+        return group;
+    };
+
+    let index = group.children.iter()
+        .position(|node| matches!(node, Node::Group(child) if child.name == *name));
+
+    let index = index.unwrap_or_else(|| {
+        group.add_child(Group::new(name));
+        group.children.len() - 1
+    });
+
+    match &mut group.children[index] {
+        Node::Group(child) => ensure_group(child, path),
+        Node::Entry(_) => unreachable!(),
+    }
+}
+
+fn join_name(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        String::from(name)
+    }
+    else {
+        format!("{}{}{}", path, NAME_SEPARATOR, name)
+    }
+}
@@ -0,0 +1,58 @@
+use zeroize::Zeroizing;
+
+use super::ExchangeRecord;
+
+/// Login as stored by other password managers
+pub struct LoginRecord {
+    pub folder: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub username: String,
+    pub password: Zeroizing<String>,
+    pub notes: String,
+}
+
+impl LoginRecord {
+    /// Names entry `folder/title` falling back to url host, username and url go to description
+    pub fn into_record(self) -> ExchangeRecord {
+        let title = if !self.title.trim().is_empty() {
+            String::from(self.title.trim())
+        }
+        else if !self.url.trim().is_empty() {
+            String::from(url_host(self.url.trim()))
+        }
+        else {
+            String::from("login")
+        };
+
+        let name = match self.folder.as_deref().map(str::trim) {
+            Some(folder) if !folder.is_empty() => format!("{}/{}", folder, title),
+            _ => title,
+        };
+
+        let mut description = Vec::new();
+        if !self.username.is_empty() {
+            description.push(format!("Username: {}", self.username));
+        }
+        if !self.url.is_empty() {
+            description.push(format!("URL: {}", self.url));
+        }
+        if !self.notes.is_empty() {
+            description.push(self.notes);
+        }
+
+        ExchangeRecord {
+            name,
+            description: description.join("\n"),
+            secret: self.password,
+        }
+    }
+}
+
+fn url_host(url: &str) -> &str {
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let url = url.split(['/', '?', '#']).next().unwrap_or(url);
+    let url = url.rsplit_once('@').map(|(_, host)| host).unwrap_or(url);
+
+    url.split(':').next().unwrap_or(url)
+}
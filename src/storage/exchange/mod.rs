@@ -0,0 +1,16 @@
+mod bitwarden_exchange;
+mod browser_exchange;
+mod csv_exchange;
+mod dotenv_exchange;
+mod exchange_content;
+mod exchange_format;
+mod exchange_record;
+mod json_exchange;
+mod kdbx_exchange;
+mod login_record;
+mod one_password_exchange;
+
+pub use exchange_content::ExchangeContent;
+pub use exchange_format::ExchangeFormat;
+pub use exchange_record::ExchangeRecord;
+use login_record::LoginRecord;
@@ -0,0 +1,150 @@
+use std::io::{Cursor, Read};
+
+use serde::Deserialize;
+use zeroize::Zeroizing;
+use zip::{result::ZipError, ZipArchive};
+
+use crate::errors::Error;
+
+use super::{ExchangeContent, LoginRecord, csv_exchange};
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const EXPORT_DATA: &str = "export.data";
+const LOGIN_CATEGORY: &str = "001";
+const PASSWORD_CATEGORY: &str = "005";
+
+#[derive(Deserialize)]
+struct OnePasswordExport {
+    accounts: Vec<OnePasswordAccount>,
+}
+
+#[derive(Deserialize)]
+struct OnePasswordAccount {
+    vaults: Vec<OnePasswordVault>,
+}
+
+#[derive(Deserialize)]
+struct OnePasswordVault {
+    attrs: OnePasswordVaultAttrs,
+    items: Vec<OnePasswordItem>,
+}
+
+#[derive(Deserialize)]
+struct OnePasswordVaultAttrs {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OnePasswordItem {
+    #[serde(default)]
+    state: String,
+    category_uuid: String,
+    details: OnePasswordDetails,
+    overview: OnePasswordOverview,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OnePasswordDetails {
+    #[serde(default)]
+    login_fields: Vec<OnePasswordLoginField>,
+    notes_plain: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OnePasswordLoginField {
+    #[serde(default)]
+    value: String,
+    designation: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OnePasswordOverview {
+    #[serde(default)]
+    title: String,
+    url: Option<String>,
+}
+
+/// Reads 1Password 1PUX archive or CSV export, detected by content
+pub fn read(mut reader: impl Read) -> Result<ExchangeContent, Error> {
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content)?;
+
+    if content.starts_with(ZIP_MAGIC) {
+        read_1pux(content)
+    }
+    else {
+        read_csv(content.as_slice())
+    }
+}
+
+fn read_1pux(content: Vec<u8>) -> Result<ExchangeContent, Error> {
+    let mut archive = ZipArchive::new(Cursor::new(content)).map_err(invalid_archive)?;
+    let export_data = archive.by_name(EXPORT_DATA).map_err(invalid_archive)?;
+
+    let export: OnePasswordExport = serde_json::from_reader(export_data)
+        .map_err(|error| Error::InvalidData(error.to_string()))?;
+
+    let mut result = ExchangeContent::from(Vec::new());
+
+    for vault in export.accounts.into_iter().flat_map(|account| account.vaults) {
+        for item in vault.items {
+            let active = item.state.is_empty() || item.state == "active";
+            let login = item.category_uuid == LOGIN_CATEGORY || item.category_uuid == PASSWORD_CATEGORY;
+
+            if !active || !login {
+                result.skipped += 1;
+                continue;
+            }
+
+            let designated = |designation: &str| item.details.login_fields.iter()
+                .find(|field| field.designation.as_deref() == Some(designation))
+                .map(|field| field.value.clone());
+
+            let login = LoginRecord {
+                folder: Some(vault.attrs.name.clone()),
+                title: item.overview.title.clone(),
+                url: item.overview.url.clone().unwrap_or_default(),
+                username: designated("username").unwrap_or_default(),
+                password: Zeroizing::new(designated("password")
+                    .or_else(|| item.details.password.clone())
+                    .unwrap_or_default()),
+                notes: item.details.notes_plain.clone().unwrap_or_default(),
+            };
+
+            result.records.push(login.into_record());
+        }
+    }
+
+    Ok(result)
+}
+
+fn read_csv(content: &[u8]) -> Result<ExchangeContent, Error> {
+    let mut result = ExchangeContent::from(Vec::new());
+
+    for mut row in csv_exchange::read_table(content)? {
+        if csv_exchange::column(&mut row, &["archived"]) == "true" {
+            result.skipped += 1;
+            continue;
+        }
+
+        let login = LoginRecord {
+            folder: None,
+            title: csv_exchange::column(&mut row, &["title"]),
+            url: csv_exchange::column(&mut row, &["url", "website", "login url"]),
+            username: csv_exchange::column(&mut row, &["username", "login username"]),
+            password: Zeroizing::new(csv_exchange::column(&mut row, &["password", "login password"])),
+            notes: csv_exchange::column(&mut row, &["notes", "notesplain"]),
+        };
+
+        result.records.push(login.into_record());
+    }
+
+    Ok(result)
+}
+
+fn invalid_archive(error: ZipError) -> Error {
+    Error::InvalidData(format!("Invalid 1PUX archive: {}", error))
+}
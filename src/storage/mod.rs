@@ -1,2 +1,4 @@
 pub mod registries;
-pub mod entries;
\ No newline at end of file
+pub mod entries;
+pub mod exchange;
+pub mod backups;
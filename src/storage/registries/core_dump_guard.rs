@@ -0,0 +1,139 @@
+use std::sync::Mutex;
+
+/// Keeps core dumps of the process disabled while any guard is alive. On Linux the process is also
+/// marked not dumpable, which a raised hard limit can not undo and which keeps other processes of the
+/// same user from attaching to it. The previous limit and flag are restored when the last guard is dropped.
+pub struct CoreDumpGuard;
+
+impl CoreDumpGuard {
+    pub fn acquire() -> Self {
+        let mut state = STATE.lock().unwrap_or_else(|error| error.into_inner());
+
+        if state.guards == 0 {
+            state.previous = disable_core_dumps();
+            state.dumpable = disable_dumpable();
+        }
+        state.guards += 1;
+
+        Self
+    }
+}
+
+impl Drop for CoreDumpGuard {
+    fn drop(&mut self) {
+        let mut state = STATE.lock().unwrap_or_else(|error| error.into_inner());
+
+        state.guards -= 1;
+        if state.guards == 0 {
+            if let Some(previous) = state.previous.take() {
+                restore_core_dumps(previous);
+            }
+            if let Some(dumpable) = state.dumpable.take() {
+                restore_dumpable(dumpable);
+            }
+        }
+    }
+}
+
+struct CoreDumpState {
+    guards: usize,
+    previous: Option<CoreLimit>,
+    dumpable: Option<DumpableFlag>,
+}
+
+#[cfg(unix)]
+type CoreLimit = libc::rlim_t;
+
+#[cfg(not(unix))]
+type CoreLimit = u64;
+
+#[cfg(target_os = "linux")]
+type DumpableFlag = libc::c_int;
+
+#[cfg(not(target_os = "linux"))]
+type DumpableFlag = i32;
+
+static STATE: Mutex<CoreDumpState> = Mutex::new(CoreDumpState {
+    guards: 0,
+    previous: None,
+    dumpable: None,
+});
+
+/// Sets the soft core size limit to zero, returning the previous soft limit.
+#[cfg(unix)]
+fn disable_core_dumps() -> Option<CoreLimit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    // SAFETY: `limit` is a valid rlimit for the duration of both calls.
+    unsafe {
+        if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) != 0 {
+            return None;
+        }
+
+        let previous = limit.rlim_cur;
+        limit.rlim_cur = 0;
+
+        if libc::setrlimit(libc::RLIMIT_CORE, &limit) != 0 {
+            return None;
+        }
+
+        Some(previous)
+    }
+}
+
+#[cfg(unix)]
+fn restore_core_dumps(previous: CoreLimit) {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    // SAFETY: `limit` is a valid rlimit for the duration of both calls.
+    unsafe {
+        if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) == 0 {
+            limit.rlim_cur = previous;
+            libc::setrlimit(libc::RLIMIT_CORE, &limit);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn disable_core_dumps() -> Option<CoreLimit> {
+    None
+}
+
+#[cfg(not(unix))]
+fn restore_core_dumps(_previous: CoreLimit) {}
+
+/// Marks the process not dumpable, returning the previous flag.
+#[cfg(target_os = "linux")]
+fn disable_dumpable() -> Option<DumpableFlag> {
+    // SAFETY: PR_GET_DUMPABLE and PR_SET_DUMPABLE take no pointer arguments.
+    unsafe {
+        let previous = libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0);
+        if previous < 0 || libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) != 0 {
+            return None;
+        }
+
+        Some(previous)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn restore_dumpable(previous: DumpableFlag) {
+    // SAFETY: PR_SET_DUMPABLE takes no pointer arguments.
+    unsafe {
+        libc::prctl(libc::PR_SET_DUMPABLE, previous as libc::c_ulong, 0, 0, 0);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn disable_dumpable() -> Option<DumpableFlag> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restore_dumpable(_previous: DumpableFlag) {}
@@ -0,0 +1,393 @@
+use std::{fs::{self, File}, io::{self, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}, time::Duration};
+
+use crate::{errors::Error, storage::entries::EntryOperationDto};
+
+use super::{
+    operation_codec::{encode_frame, decode_payload, read_operation_payload, read_i32, read_i32_option, read_bytes_array, FRAME_TAG},
+    registry_header::{RegistryHeader, FLAG_TEAM},
+    header_signature::SIGNATURE_SIZE,
+    registry_lock::{RegistryLock, LockMode},
+    registry_member::read_members,
+    registry_storage::{RegistryStorage, archive, remove_files, sync_parent, TEMP_EXTENSION, LOCK_EXTENSION},
+};
+
+/// Candidate frames checked for intact records behind a frame overrunning the log.
+const FRAME_CANDIDATES: usize = 16;
+
+/// Registry stored as a single file: the header region followed by framed operations.
+pub struct FileStorage {
+    file: File,
+    path: PathBuf,
+    log_offset: u64,
+    torn_tail: Option<u64>,
+    lock: Option<RegistryLock>,
+}
+
+impl FileStorage {
+    /// Creates an empty registry file at `path`, failing if it already exists.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let lock = RegistryLock::acquire(&path.with_extension(LOCK_EXTENSION), LockMode::Exclusive, Duration::ZERO)?;
+
+        if path.exists() {
+            return Err(Error::RegistryExists(path.display().to_string()));
+        }
+
+        let result = Self {
+            file: create_file(path)?,
+            path: path.to_path_buf(),
+            log_offset: 0,
+            torn_tail: None,
+            lock: Some(lock),
+        };
+
+        Ok(result)
+    }
+
+    /// Opens the registry file at `path`, waiting up to `lock_timeout` for the lock.
+    pub fn open(path: &Path, lock_mode: LockMode, lock_timeout: Duration) -> Result<Self, Error> {
+        if !path.is_file() {
+            return Err(Error::NoSuchRegistry(path.display().to_string()));
+        }
+
+        let lock = RegistryLock::acquire(&path.with_extension(LOCK_EXTENSION), lock_mode, lock_timeout)?;
+
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .open(path)?;
+
+        Self::load(file, path.to_path_buf(), Some(lock))
+    }
+
+    /// Writes `header_region` to an unlocked temporary file next to `path`.
+    pub fn stage(path: &Path, header_region: &[u8]) -> Result<Self, Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let path = path.with_extension(TEMP_EXTENSION);
+        let mut file = create_file(&path)?;
+        file.write_all(header_region)?;
+        file.rewind()?;
+
+        let result = Self::load(file, path, None)?;
+
+        if result.log_offset != header_region.len() as u64 {
+            return Err(Error::InvalidData(String::from("Staged registry header is malformed")));
+        }
+
+        Ok(result)
+    }
+
+    fn load(mut file: File, path: PathBuf, lock: Option<RegistryLock>) -> Result<Self, Error> {
+        let header = RegistryHeader::read(&mut file)?;
+
+        let mut keys = vec![0; header.public_key_size as usize + header.private_key_size as usize];
+        file.read_exact(keys.as_mut_slice())?;
+
+        if header.flags & FLAG_TEAM != 0 {
+            read_members(&mut file)?;
+        }
+        if header.signed() {
+            read_bytes_array::<SIGNATURE_SIZE>(&mut file)?;
+        }
+
+        let log_offset = file.stream_position()?;
+
+        let result = Self {
+            file,
+            path,
+            log_offset,
+            torn_tail: None,
+            lock,
+        };
+
+        Ok(result)
+    }
+
+    /// Whether a complete frame ends the log after `from`. A record cut short by a crash is the last one
+    /// written, so a frame overrunning the log with intact frames behind it is corruption instead.
+    /// Records written after the corrupt one end at the end of the log, so only frames ending there are checked.
+    fn frame_follows(&mut self, from: u64) -> Result<bool, Error> {
+        let mut rest = Vec::new();
+        self.file.seek(SeekFrom::Start(from))?;
+        self.file.read_to_end(&mut rest)?;
+
+        let result = (0..rest.len())
+            .filter(|start| {
+                let Some((tag, frame)) = rest[*start..].split_first_chunk::<4>() else {
+                    return false;
+                };
+                let Some((length, _)) = frame.split_first_chunk::<4>() else {
+                    return false;
+                };
+
+                let length = i32::from_le_bytes(*length);
+                i32::from_le_bytes(*tag) == FRAME_TAG && length >= 0 && start + 12 + length as usize == rest.len()
+            })
+            .take(FRAME_CANDIDATES)
+            .any(|start| {
+                let (payload, checksum) = rest[start + 8..].split_at(rest.len() - start - 12);
+                crc32fast::hash(payload).to_le_bytes() == checksum
+            });
+
+        Ok(result)
+    }
+
+    fn torn(&mut self, offset: u64) -> Result<Option<EntryOperationDto>, Error> {
+        self.torn_tail = Some(offset);
+        self.file.seek(SeekFrom::Start(offset))?;
+        Ok(None)
+    }
+}
+
+impl RegistryStorage for FileStorage {
+    fn kind(&self) -> &'static str {
+        "file"
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn writable(&self) -> bool {
+        self.lock.as_ref().is_none_or(|lock| lock.mode == LockMode::Exclusive)
+    }
+
+    fn read_header(&self) -> Result<Vec<u8>, Error> {
+        let mut result = vec![0; self.log_offset as usize];
+        File::open(&self.path)?.read_exact(result.as_mut_slice())?;
+        Ok(result)
+    }
+
+    fn write_header(&mut self, header_region: &[u8]) -> Result<(), Error> {
+        self.file.rewind()?;
+        self.file.write_all(header_region)?;
+        self.file.sync_all()?;
+
+        self.log_offset = header_region.len() as u64;
+
+        Ok(())
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(self.log_offset))?;
+        self.torn_tail = None;
+        Ok(())
+    }
+
+    fn position(&mut self) -> Result<u64, Error> {
+        Ok(self.file.stream_position()?)
+    }
+
+    fn read_operation(&mut self) -> Result<Option<EntryOperationDto>, Error> {
+        let offset = self.file.stream_position()?;
+        let size = self.size()?;
+
+        let tag = match read_i32_option(&mut self.file) {
+            Ok(Some(tag)) => tag,
+            Ok(None) => return Ok(None),
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return self.torn(offset),
+            Err(error) => return Err(error.into()),
+        };
+
+        if tag != FRAME_TAG {
+            return match read_operation_payload(&mut self.file, tag) {
+                Ok(result) => Ok(Some(result)),
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => self.torn(offset),
+                Err(error) => Err(corrupt_log(offset, error)),
+            };
+        }
+
+        let length = match read_i32(&mut self.file) {
+            Ok(length) => length,
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return self.torn(offset),
+            Err(error) => return Err(error.into()),
+        };
+
+        if length < 0 {
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("negative frame length"),
+            });
+        }
+
+        let frame_end = offset.checked_add(12 + length as u64).ok_or_else(|| Error::CorruptLog {
+            offset,
+            reason: String::from("frame length overflows"),
+        })?;
+
+        if frame_end > size {
+            if self.frame_follows(offset + 8)? {
+                return Err(Error::CorruptLog {
+                    offset,
+                    reason: String::from("frame length exceeds log while records follow"),
+                });
+            }
+
+            return self.torn(offset);
+        }
+
+        let mut payload = vec![0; length as usize];
+        self.file.read_exact(payload.as_mut_slice())?;
+        let checksum = read_bytes_array::<4>(&mut self.file)?;
+
+        if crc32fast::hash(&payload).to_le_bytes() != checksum {
+            if frame_end == size {
+                return self.torn(offset);
+            }
+
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("frame checksum mismatch"),
+            });
+        }
+
+        decode_payload(&payload)
+            .map(Some)
+            .map_err(|error| corrupt_log(offset, error))
+    }
+
+    fn append_operation(&mut self, operation: &EntryOperationDto) -> Result<(), Error> {
+        if let Some(offset) = self.torn_tail {
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("torn record, use repair --truncate-tail before writing"),
+            });
+        }
+
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&encode_frame(operation))?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    fn sync(&mut self) -> Result<(), Error> {
+        Ok(self.file.sync_all()?)
+    }
+
+    fn create_replacement(&self, header_region: &[u8]) -> Result<Box<dyn RegistryStorage>, Error> {
+        let path = self.path.with_extension(TEMP_EXTENSION);
+        let mut file = create_file(&path)?;
+        file.write_all(header_region)?;
+
+        let result = Self {
+            file,
+            path,
+            log_offset: header_region.len() as u64,
+            torn_tail: None,
+            lock: None,
+        };
+
+        Ok(Box::new(result))
+    }
+
+    fn replace(self: Box<Self>, mut replacement: Box<dyn RegistryStorage>, archive_current: bool) -> Result<Option<PathBuf>, Error> {
+        replacement.sync()?;
+
+        let replacement_path = replacement.path()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| Error::Unsupported(String::from("Registry file can only be replaced by another file")))?;
+
+        let archive_path = if archive_current {
+            Some(archive(&self.path)?)
+        }
+        else {
+            None
+        };
+
+        fs::rename(replacement_path, &self.path)?;
+        sync_parent(&self.path)?;
+
+        Ok(archive_path)
+    }
+
+    fn install(self: Box<Self>, path: &Path) -> Result<(), Error> {
+        self.file.sync_all()?;
+        fs::rename(&self.path, path)?;
+        sync_parent(path)?;
+
+        Ok(())
+    }
+
+    fn discard(self: Box<Self>) -> Result<(), Error> {
+        if self.lock.is_some() {
+            return Err(Error::NotPermitted(String::from("Only staged registry can be discarded")));
+        }
+
+        fs::remove_file(&self.path)?;
+
+        Ok(())
+    }
+
+    fn remove(self: Box<Self>) -> Result<(), Error> {
+        remove_files(&self.path)
+    }
+
+    fn torn_tail(&self) -> Option<u64> {
+        self.torn_tail
+    }
+
+    fn truncate_tail(&mut self) -> Result<u64, Error> {
+        if let Some(offset) = self.torn_tail {
+            let size = self.size()?;
+
+            self.file.set_len(offset)?;
+            self.file.sync_all()?;
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.torn_tail = None;
+
+            Ok(size - offset)
+        }
+        else {
+            Ok(0)
+        }
+    }
+
+    fn read_log(&mut self) -> Result<Vec<u8>, Error> {
+        let mut source = File::open(&self.path)?;
+        source.seek(SeekFrom::Start(self.log_offset))?;
+
+        let mut result = Vec::new();
+        source.read_to_end(&mut result)?;
+
+        if let Some(offset) = self.torn_tail {
+            result.truncate((offset - self.log_offset) as usize);
+        }
+
+        Ok(result)
+    }
+
+    fn append_log(&mut self, log: &[u8]) -> Result<(), Error> {
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(log)?;
+        self.file.sync_data()?;
+
+        self.rewind()
+    }
+}
+
+fn create_file(path: &Path) -> Result<File, io::Error> {
+    File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
+
+fn corrupt_log(offset: u64, error: io::Error) -> Error {
+    Error::CorruptLog {
+        offset,
+        reason: error.to_string(),
+    }
+}
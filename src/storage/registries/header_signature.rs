@@ -0,0 +1,36 @@
+use libsecp256k1::{sign, verify, Message, PublicKey, SecretKey, Signature};
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::Error;
+
+/// Size of the signature closing the header region of current registries.
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// Signs `region` with the registry private key, so flags and members can not be changed without it.
+pub fn sign_region(region: &[u8], private_key: &[u8]) -> Result<[u8; SIGNATURE_SIZE], Error> {
+    let secret_key = SecretKey::parse_slice(private_key)
+        .map_err(|_| Error::InvalidData(String::from("Registry private key is malformed")))?;
+
+    let (signature, _) = sign(&region_message(region), &secret_key);
+
+    Ok(signature.serialize())
+}
+
+/// Checks `signature` of `region` against the registry public key.
+pub fn verify_region(region: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    let (Ok(signature), Ok(public_key)) = (Signature::parse_standard_slice(signature), PublicKey::parse_slice(public_key, None)) else {
+        return false;
+    };
+
+    verify(&region_message(region), &signature, &public_key)
+}
+
+const SIGNATURE_CONTEXT: &[u8] = b"enigmatic/header-signature";
+
+fn region_message(region: &[u8]) -> Message {
+    let mut hasher = Sha3_256::new();
+    hasher.update(SIGNATURE_CONTEXT);
+    hasher.update(region);
+
+    Message::parse(&hasher.finalize().into())
+}
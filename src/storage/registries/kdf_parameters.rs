@@ -0,0 +1,42 @@
+use rand::RngCore;
+use rand_core::OsRng;
+
+#[derive(Clone)]
+pub struct KdfParameters {
+    pub kdf_type: i32,
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub salt: [u8; 16],
+}
+
+pub const KDF_NONE: i32 = 0;
+pub const KDF_ARGON2ID: i32 = 1;
+
+pub const DEFAULT_KDF_MEMORY: u32 = 64 * 1024;
+pub const DEFAULT_KDF_ITERATIONS: u32 = 3;
+
+impl KdfParameters {
+    pub fn none() -> Self {
+        Self {
+            kdf_type: KDF_NONE,
+            memory: 0,
+            iterations: 0,
+            parallelism: 0,
+            salt: [0u8; 16],
+        }
+    }
+
+    pub fn argon2id(memory: u32, iterations: u32) -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        Self {
+            kdf_type: KDF_ARGON2ID,
+            memory,
+            iterations,
+            parallelism: 1,
+            salt,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{aead::{Aead, Payload}, KeyInit, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use rand_core::OsRng;
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+use super::{kdf_parameters::{KdfParameters, KDF_NONE, KDF_ARGON2ID}, registry_header::UNSIGNED_REGISTRY_VERSION};
+
+/// Encrypts `private_key` with `password` for a registry of format `version`.
+pub fn wrap_private_key(
+    private_key: &[u8], 
+    public_key: &[u8], 
+    version: i32,
+    password: &str, 
+    kdf: &KdfParameters,
+) -> Result<Vec<u8>, Error> {
+    match kdf.kdf_type {
+        KDF_NONE => Ok(simplecrypt::encrypt(private_key, password.as_bytes())),
+        KDF_ARGON2ID => {
+            let cipher = XChaCha20Poly1305::new(derive_key(password, kdf)?.as_slice().into());
+
+            let mut nonce = [0u8; NONCE_SIZE];
+            OsRng.fill_bytes(&mut nonce);
+
+            let aad = associated_data(public_key, version);
+            let payload = Payload {
+                msg: private_key,
+                aad: &aad,
+            };
+            let ciphertext = cipher.encrypt(XNonce::from_slice(&nonce), payload)
+                .map_err(|_| Error::InvalidInput(String::from("Can not encrypt private key")))?;
+
+            let mut result = nonce.to_vec();
+            result.extend(ciphertext);
+            Ok(result)
+        },
+        kdf_type => Err(unsupported_kdf(kdf_type)),
+    }
+}
+
+pub fn unwrap_private_key(
+    encrypted_private_key: &[u8], 
+    public_key: &[u8], 
+    version: i32,
+    password: &str, 
+    kdf: &KdfParameters,
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    match kdf.kdf_type {
+        KDF_NONE => simplecrypt::decrypt(encrypted_private_key, password.as_bytes())
+            .map(Zeroizing::new)
+            .map_err(|_| Error::WrongPassword),
+        KDF_ARGON2ID => {
+            if encrypted_private_key.len() < NONCE_SIZE {
+                return Err(Error::InvalidData(String::from("Registry private key is malformed")));
+            }
+
+            let cipher = XChaCha20Poly1305::new(derive_key(password, kdf)?.as_slice().into());
+
+            let (nonce, ciphertext) = encrypted_private_key.split_at(NONCE_SIZE);
+            let aad = associated_data(public_key, version);
+            let payload = Payload {
+                msg: ciphertext,
+                aad: &aad,
+            };
+
+            cipher.decrypt(XNonce::from_slice(nonce), payload)
+                .map(Zeroizing::new)
+                .map_err(|_| Error::WrongPassword)
+        },
+        kdf_type => Err(unsupported_kdf(kdf_type)),
+    }
+}
+
+/// Binds the wrapped key to the public key and, since signed headers, to the format version,
+/// so a registry rewritten as an unsigned version can not be unlocked.
+fn associated_data(public_key: &[u8], version: i32) -> Vec<u8> {
+    let mut result = public_key.to_vec();
+    if version > UNSIGNED_REGISTRY_VERSION {
+        result.extend_from_slice(&version.to_le_bytes());
+    }
+
+    result
+}
+
+fn derive_key(password: &str, kdf: &KdfParameters) -> Result<Zeroizing<[u8; 32]>, Error> {
+    let params = Params::new(kdf.memory, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|error| Error::InvalidInput(format!("Invalid KDF parameters: {}", error)))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), &kdf.salt, key.as_mut_slice())
+        .map_err(|error| Error::InvalidInput(format!("Can not derive key: {}", error)))?;
+
+    Ok(key)
+}
+
+fn unsupported_kdf(kdf_type: i32) -> Error {
+    Error::Unsupported(format!("Unsupported key derivation function {}", kdf_type))
+}
+
+const NONCE_SIZE: usize = 24;
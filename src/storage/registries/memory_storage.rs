@@ -0,0 +1,133 @@
+use std::{path::{Path, PathBuf}, sync::{Arc, Mutex, MutexGuard}};
+
+use crate::{errors::Error, storage::entries::EntryOperationDto};
+
+use super::{operation_codec::encode_frame, registry_storage::RegistryStorage};
+
+/// Registry kept in memory, for tests and embedding.
+///
+/// Clones share the same registry, so a clone kept aside sees everything written through the repository.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    registry: Arc<Mutex<MemoryRegistry>>,
+    cursor: usize,
+}
+
+#[derive(Default)]
+struct MemoryRegistry {
+    header_region: Vec<u8>,
+    operations: Vec<EntryOperationDto>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn registry(&self) -> MutexGuard<'_, MemoryRegistry> {
+        self.registry.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl RegistryStorage for MemoryStorage {
+    fn kind(&self) -> &'static str {
+        "memory"
+    }
+
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn read_header(&self) -> Result<Vec<u8>, Error> {
+        let registry = self.registry();
+        if registry.header_region.is_empty() {
+            return Err(Error::InvalidData(String::from("In-memory registry has no header")));
+        }
+
+        Ok(registry.header_region.clone())
+    }
+
+    fn write_header(&mut self, header_region: &[u8]) -> Result<(), Error> {
+        self.registry().header_region = header_region.to_vec();
+        Ok(())
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.cursor = 0;
+        Ok(())
+    }
+
+    fn position(&mut self) -> Result<u64, Error> {
+        Ok(self.cursor as u64)
+    }
+
+    fn read_operation(&mut self) -> Result<Option<EntryOperationDto>, Error> {
+        let result = self.registry().operations.get(self.cursor).cloned();
+        if result.is_some() {
+            self.cursor += 1;
+        }
+
+        Ok(result)
+    }
+
+    fn append_operation(&mut self, operation: &EntryOperationDto) -> Result<(), Error> {
+        let mut registry = self.registry();
+        registry.operations.push(operation.clone());
+        let cursor = registry.operations.len();
+        drop(registry);
+
+        self.cursor = cursor;
+
+        Ok(())
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        let registry = self.registry();
+        let log_size: usize = registry.operations.iter()
+            .map(|operation| encode_frame(operation).len())
+            .sum();
+
+        Ok((registry.header_region.len() + log_size) as u64)
+    }
+
+    fn create_replacement(&self, header_region: &[u8]) -> Result<Box<dyn RegistryStorage>, Error> {
+        let mut result = Self::new();
+        result.write_header(header_region)?;
+        Ok(Box::new(result))
+    }
+
+    fn replace(self: Box<Self>, mut replacement: Box<dyn RegistryStorage>, _archive: bool) -> Result<Option<PathBuf>, Error> {
+        let header_region = replacement.read_header()?;
+
+        let mut operations = Vec::new();
+        replacement.rewind()?;
+        while let Some(operation) = replacement.read_operation()? {
+            operations.push(operation);
+        }
+
+        let mut registry = self.registry();
+        registry.header_region = header_region;
+        registry.operations = operations;
+
+        Ok(None)
+    }
+
+    fn install(self: Box<Self>, _path: &Path) -> Result<(), Error> {
+        Err(Error::Unsupported(String::from("In-memory registry can not be installed to a path")))
+    }
+
+    fn discard(self: Box<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn remove(self: Box<Self>) -> Result<(), Error> {
+        let mut registry = self.registry();
+        registry.header_region.clear();
+        registry.operations.clear();
+        Ok(())
+    }
+}
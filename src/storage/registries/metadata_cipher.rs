@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use ecies::{encrypt, decrypt};
+use sha3::{Digest, Sha3_256};
+use zeroize::Zeroizing;
+
+use crate::{errors::Error, storage::entries::EntryOperationDto};
+
+use super::operation_codec::{encode_metadata, decode_metadata};
+
+/// Seals entry names and descriptions of operations stored in a registry with private metadata.
+///
+/// Every name is stored as a keyed hash, so `Set` and `Del` keep referring to entries deterministically.
+/// The name itself travels encrypted together with the description in the `Add` or `Set` introducing it.
+#[derive(Clone)]
+pub struct MetadataCipher {
+    lookup_key: Zeroizing<[u8; 32]>,
+    names: HashMap<String, String>,
+}
+
+impl MetadataCipher {
+    pub fn new(private_key: &[u8]) -> Self {
+        let mut hasher = Sha3_256::new();
+        hasher.update(LOOKUP_CONTEXT);
+        hasher.update(private_key);
+
+        Self {
+            lookup_key: Zeroizing::new(hasher.finalize().into()),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Keyed lookup of entry `name` as stored in the log.
+    pub fn lookup(&self, name: &str) -> String {
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.lookup_key.as_slice());
+        hasher.update(name.as_bytes());
+
+        to_hex(&hasher.finalize())
+    }
+
+    pub fn seal(&mut self, operation: &EntryOperationDto, public_key: &[u8]) -> Result<EntryOperationDto, Error> {
+        let result = match operation {
+            EntryOperationDto::Add { hash, timestamp, name, description, secret } => {
+                EntryOperationDto::Add {
+                    hash: *hash,
+                    timestamp: *timestamp,
+                    name: self.remember(name),
+                    description: seal_metadata(public_key, Some(name), Some(description))?,
+                    secret: secret.clone(),
+                }
+            },
+            EntryOperationDto::Set { hash, timestamp, src_name, dst_name, dst_description, dst_secret } => {
+                let metadata = if dst_name.is_some() || dst_description.is_some() {
+                    Some(seal_metadata(public_key, dst_name.as_deref(), dst_description.as_deref())?)
+                }
+                else {
+                    None
+                };
+
+                EntryOperationDto::Set {
+                    hash: *hash,
+                    timestamp: *timestamp,
+                    src_name: self.lookup(src_name),
+                    dst_name: dst_name.as_deref().map(|dst_name| self.remember(dst_name)),
+                    dst_description: metadata,
+                    dst_secret: dst_secret.clone(),
+                }
+            },
+            EntryOperationDto::Del { hash, timestamp, name } => {
+                EntryOperationDto::Del {
+                    hash: *hash,
+                    timestamp: *timestamp,
+                    name: self.lookup(name),
+                }
+            },
+        };
+
+        Ok(result)
+    }
+
+    /// Restores names and descriptions of sealed `operation`. Lookups are resolved through the names
+    /// introduced by operations opened before, so the log must be read from its start.
+    pub fn open(&mut self, operation: EntryOperationDto, private_key: &[u8]) -> Result<EntryOperationDto, &'static str> {
+        let result = match operation {
+            EntryOperationDto::Add { hash, timestamp, name, description, secret } => {
+                let (Some(plain_name), Some(description)) = open_metadata(private_key, &description)? else {
+                    return Err("sealed entry metadata is incomplete");
+                };
+
+                EntryOperationDto::Add {
+                    hash,
+                    timestamp,
+                    name: self.introduce(&name, plain_name)?,
+                    description,
+                    secret,
+                }
+            },
+            EntryOperationDto::Set { hash, timestamp, src_name, dst_name, dst_description, dst_secret } => {
+                let (plain_name, description) = match dst_description {
+                    Some(metadata) => open_metadata(private_key, &metadata)?,
+                    None => (None, None),
+                };
+
+                let dst_name = match (dst_name, plain_name) {
+                    (Some(dst_name), Some(plain_name)) => Some(self.introduce(&dst_name, plain_name)?),
+                    (None, None) => None,
+                    _ => return Err("sealed entry metadata does not match entry lookup"),
+                };
+
+                EntryOperationDto::Set {
+                    hash,
+                    timestamp,
+                    src_name: self.resolve(&src_name)?,
+                    dst_name,
+                    dst_description: description,
+                    dst_secret,
+                }
+            },
+            EntryOperationDto::Del { hash, timestamp, name } => {
+                EntryOperationDto::Del {
+                    hash,
+                    timestamp,
+                    name: self.resolve(&name)?,
+                }
+            },
+        };
+
+        Ok(result)
+    }
+
+    fn remember(&mut self, name: &str) -> String {
+        let lookup = self.lookup(name);
+        self.names.insert(lookup.clone(), String::from(name));
+        lookup
+    }
+
+    fn introduce(&mut self, lookup: &str, name: String) -> Result<String, &'static str> {
+        if self.lookup(&name) != lookup {
+            return Err("sealed entry metadata does not match entry lookup");
+        }
+
+        self.names.insert(String::from(lookup), name.clone());
+        Ok(name)
+    }
+
+    fn resolve(&self, lookup: &str) -> Result<String, &'static str> {
+        self.names.get(lookup)
+            .cloned()
+            .ok_or("operation refers to an unknown entry")
+    }
+}
+
+const LOOKUP_CONTEXT: &[u8] = b"enigmatic/metadata-lookup";
+
+fn seal_metadata(public_key: &[u8], name: Option<&str>, description: Option<&str>) -> Result<String, Error> {
+    let metadata = Zeroizing::new(encode_metadata(name, description));
+
+    encrypt(public_key, &metadata)
+        .map(|sealed| to_hex(&sealed))
+        .map_err(|_| Error::InvalidData(String::from("Registry public key is malformed")))
+}
+
+fn open_metadata(private_key: &[u8], sealed: &str) -> Result<(Option<String>, Option<String>), &'static str> {
+    let sealed = from_hex(sealed).ok_or("sealed entry metadata is malformed")?;
+    let metadata = decrypt(private_key, &sealed)
+        .map(Zeroizing::new)
+        .map_err(|_| "sealed entry metadata can not be decrypted")?;
+
+    decode_metadata(&metadata).map_err(|_| "sealed entry metadata is malformed")
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(data: &str) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..data.len())
+        .step_by(2)
+        .map(|index| data.get(index..index + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
@@ -1,4 +1,29 @@
+mod core_dump_guard;
+mod file_storage;
+mod header_signature;
+mod kdf_parameters;
+mod key_wrapping;
+mod memory_storage;
+mod metadata_cipher;
+mod operation_codec;
+mod private_key;
+mod registry_catalog;
 mod registry_header;
+mod registry_lock;
+mod registry_member;
 mod registry_repository;
+mod registry_storage;
+mod secret_envelope;
+mod secret_padding;
+mod sqlite_storage;
 
-pub use registry_repository::RegistryRepository;
\ No newline at end of file
+pub use file_storage::FileStorage;
+pub use kdf_parameters::{KdfParameters, KDF_ARGON2ID, DEFAULT_KDF_MEMORY, DEFAULT_KDF_ITERATIONS};
+pub use memory_storage::MemoryStorage;
+pub use registry_catalog::RegistryCatalog;
+pub use registry_lock::LockMode;
+pub use registry_member::RegistryMember;
+pub use registry_repository::RegistryRepository;
+pub use registry_storage::RegistryStorage;
+pub use secret_padding::{SecretPadding, MIN_PADDED_SIZE};
+pub use sqlite_storage::SqliteStorage;
@@ -0,0 +1,239 @@
+use std::io::{self, Read};
+
+use crate::{errors::Error, storage::entries::EntryOperationDto};
+
+pub const FRAME_TAG: i32 = 0x4D415246;
+
+/// Encodes operation as `tag, length, payload, crc32` frame
+pub fn encode_frame(operation: &EntryOperationDto) -> Vec<u8> {
+    let payload = encode_payload(operation);
+
+    let mut frame = Vec::with_capacity(payload.len() + 12);
+    frame.extend_from_slice(&FRAME_TAG.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as i32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    frame.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+
+    frame
+}
+
+/// Decodes framed and legacy unframed records of complete log
+pub fn decode_log(log: &[u8]) -> Result<Vec<EntryOperationDto>, Error> {
+    let mut result = Vec::new();
+    let mut reader = log;
+
+    while !reader.is_empty() {
+        let offset = (log.len() - reader.len()) as u64;
+
+        let operation = read_record(&mut reader).map_err(|error| Error::CorruptLog {
+            offset,
+            reason: error.to_string(),
+        })?;
+
+        result.push(operation);
+    }
+
+    Ok(result)
+}
+
+fn read_record(reader: &mut &[u8]) -> Result<EntryOperationDto, io::Error> {
+    let tag = read_i32(reader)?;
+    if tag != FRAME_TAG {
+        return read_operation_payload(reader, tag);
+    }
+
+    let length = read_i32(reader)?;
+    if length < 0 || length as usize + 4 > reader.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let (payload, rest) = reader.split_at(length as usize);
+    let (checksum, rest) = rest.split_at(4);
+    *reader = rest;
+
+    if crc32fast::hash(payload).to_le_bytes() != checksum {
+        return Err(invalid_data("Frame checksum mismatch"));
+    }
+
+    decode_payload(payload)
+}
+
+pub fn encode_payload(operation: &EntryOperationDto) -> Vec<u8> {
+    let mut result = Vec::new();
+    write_operation_payload(&mut result, operation);
+    result
+}
+
+pub fn decode_payload(mut payload: &[u8]) -> Result<EntryOperationDto, io::Error> {
+    let op_code = read_i32(&mut payload)?;
+    read_operation_payload(&mut payload, op_code)
+}
+
+/// Encodes entry name and description sealed together in operations of registries with private metadata
+pub fn encode_metadata(name: Option<&str>, description: Option<&str>) -> Vec<u8> {
+    let mut result = Vec::new();
+    write_string(&mut result, name);
+    write_string(&mut result, description);
+    result
+}
+
+pub fn decode_metadata(mut data: &[u8]) -> Result<(Option<String>, Option<String>), io::Error> {
+    let name = read_string(&mut data)?;
+    let description = read_string(&mut data)?;
+
+    if !data.is_empty() {
+        return Err(invalid_data("Trailing metadata bytes"));
+    }
+
+    Ok((name, description))
+}
+
+fn write_operation_payload(writer: &mut Vec<u8>, operation: &EntryOperationDto) {
+    match operation {
+        EntryOperationDto::Add { hash, timestamp, name, description, secret } => {
+            write_i32(writer, 1);
+            writer.extend_from_slice(hash);
+            writer.extend_from_slice(&timestamp.to_le_bytes());
+
+            write_string(writer, Some(name));
+            write_string(writer, Some(description));
+            write_bytes(writer, Some(secret));
+        },
+        EntryOperationDto::Set { hash, timestamp, src_name, dst_name, dst_description, dst_secret } => {
+            write_i32(writer, 2);
+            writer.extend_from_slice(hash);
+            writer.extend_from_slice(&timestamp.to_le_bytes());
+
+            write_string(writer, Some(src_name));
+            write_string(writer, dst_name.as_deref());
+            write_string(writer, dst_description.as_deref());
+            write_bytes(writer, dst_secret.as_deref());
+        },
+        EntryOperationDto::Del { hash, timestamp, name } => {
+            write_i32(writer, 3);
+            writer.extend_from_slice(hash);
+            writer.extend_from_slice(&timestamp.to_le_bytes());
+
+            write_string(writer, Some(name));
+        },
+    }
+}
+
+pub fn read_operation_payload(reader: &mut impl Read, op_code: i32) -> Result<EntryOperationDto, io::Error> {
+    let result = match op_code {
+        1 => {
+            EntryOperationDto::Add {
+                hash: read_bytes_array::<64>(reader)?,
+                timestamp: read_u128(reader)?,
+                name: read_required_string(reader)?,
+                description: read_required_string(reader)?,
+                secret: read_required_bytes(reader)?,
+            }
+        },
+        2 => {
+            EntryOperationDto::Set {
+                hash: read_bytes_array::<64>(reader)?,
+                timestamp: read_u128(reader)?,
+                src_name: read_required_string(reader)?,
+                dst_name: read_string(reader)?,
+                dst_description: read_string(reader)?,
+                dst_secret: read_bytes(reader)?,
+            }
+        },
+        3 => {
+            EntryOperationDto::Del {
+                hash: read_bytes_array::<64>(reader)?,
+                timestamp: read_u128(reader)?,
+                name: read_required_string(reader)?,
+            }
+        },
+        _ => return Err(invalid_data("Invalid entry operation code")),
+    };
+
+    Ok(result)
+}
+
+fn write_i32(writer: &mut Vec<u8>, data: i32) {
+    writer.extend_from_slice(&data.to_le_bytes());
+}
+
+pub fn read_i32_option(reader: &mut impl Read) -> Result<Option<i32>, io::Error> {
+    let mut buffer = [0u8; 4];
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            count => filled += count,
+        }
+    }
+    Ok(Some(i32::from_le_bytes(buffer)))
+}
+
+pub fn read_i32(reader: &mut impl Read) -> Result<i32, io::Error> {
+    let mut buffer = [0u8; 4];
+    reader.read_exact(buffer.as_mut_slice())?;
+    Ok(i32::from_le_bytes(buffer))
+}
+
+fn read_u128(reader: &mut impl Read) -> Result<u128, io::Error> {
+    let mut buffer = [0u8; 16];
+    reader.read_exact(buffer.as_mut_slice())?;
+    Ok(u128::from_le_bytes(buffer))
+}
+
+fn write_bytes(writer: &mut Vec<u8>, data: Option<&[u8]>) {
+    if let Some(data) = data {
+        write_i32(writer, data.len() as i32);
+        writer.extend_from_slice(data);
+    }
+    else {
+        write_i32(writer, -1);
+    }
+}
+
+fn read_bytes(reader: &mut impl Read) -> Result<Option<Vec<u8>>, io::Error> {
+    let size = read_i32(reader)?;
+    if size == -1 {
+        return Ok(None);
+    }
+    if size < 0 {
+        return Err(invalid_data("Invalid field size"));
+    }
+
+    let mut data = Vec::new();
+    reader.by_ref().take(size as u64).read_to_end(&mut data)?;
+    if data.len() != size as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(Some(data))
+}
+
+fn read_required_bytes(reader: &mut impl Read) -> Result<Vec<u8>, io::Error> {
+    read_bytes(reader)?.ok_or_else(|| invalid_data("Missing required field"))
+}
+
+pub fn read_bytes_array<const COUNT: usize>(reader: &mut impl Read) -> Result<[u8; COUNT], io::Error> {
+    let mut buffer = [0u8; COUNT];
+    reader.read_exact(buffer.as_mut_slice())?;
+    Ok(buffer)
+}
+
+fn write_string(writer: &mut Vec<u8>, data: Option<&str>) {
+    write_bytes(writer, data.map(|x| x.as_bytes()))
+}
+
+fn read_string(reader: &mut impl Read) -> Result<Option<String>, io::Error> {
+    read_bytes(reader)?
+        .map(|data| String::from_utf8(data).map_err(|_| invalid_data("Invalid string field")))
+        .transpose()
+}
+
+fn read_required_string(reader: &mut impl Read) -> Result<String, io::Error> {
+    read_string(reader)?.ok_or_else(|| invalid_data("Missing required field"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
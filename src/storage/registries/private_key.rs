@@ -0,0 +1,74 @@
+use std::ops::Deref;
+
+use zeroize::{Zeroize, Zeroizing};
+
+use super::core_dump_guard::CoreDumpGuard;
+
+/// Unwrapped registry private key. It is locked in memory where the OS allows,
+/// wiped on drop, and keeps core dumps disabled while it is alive.
+pub struct PrivateKey {
+    bytes: Zeroizing<Vec<u8>>,
+    locked: bool,
+    _core_dumps: CoreDumpGuard,
+}
+
+impl PrivateKey {
+    pub fn new(bytes: Zeroizing<Vec<u8>>) -> Self {
+        let core_dumps = CoreDumpGuard::acquire();
+        let locked = lock_memory(&bytes);
+
+        Self {
+            bytes,
+            locked,
+            _core_dumps: core_dumps,
+        }
+    }
+}
+
+impl Deref for PrivateKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Clone for PrivateKey {
+    fn clone(&self) -> Self {
+        Self::new(self.bytes.clone())
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) {
+        let (address, length) = (self.bytes.as_ptr(), self.bytes.len());
+
+        self.bytes.zeroize();
+
+        if self.locked {
+            unlock_memory(address, length);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lock_memory(bytes: &[u8]) -> bool {
+    // SAFETY: the range is a live allocation owned by the key and is never reallocated.
+    !bytes.is_empty() && unsafe { libc::mlock(bytes.as_ptr().cast(), bytes.len()) == 0 }
+}
+
+#[cfg(unix)]
+fn unlock_memory(address: *const u8, length: usize) {
+    // SAFETY: the range was locked by `lock_memory` and is still allocated.
+    unsafe {
+        libc::munlock(address.cast(), length);
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_memory(_bytes: &[u8]) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn unlock_memory(_address: *const u8, _length: usize) {}
@@ -0,0 +1,101 @@
+use std::{fs::{self, File}, io, path::{Path, PathBuf}};
+
+use crate::errors::Error;
+
+use super::{registry_header::RegistryHeader, sqlite_storage::{SqliteStorage, is_sqlite}};
+
+pub struct RegistryCatalog {
+    path: PathBuf,
+}
+
+pub const DEFAULT_REGISTRY: &str = "registry";
+
+impl RegistryCatalog {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn registry_path(&self, name: &str) -> Result<PathBuf, Error> {
+        let valid = !name.is_empty() && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(Error::InvalidInput(
+                format!("Invalid registry name {}. Use letters, digits, '-' and '_' only.", name),
+            ));
+        }
+
+        Ok(self.path.join(name))
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, Error> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut result = Vec::new();
+
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let Some(name) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+
+            if self.registry_path(&name).is_ok() && is_registry(&entry.path()) {
+                result.push(name);
+            }
+        }
+
+        result.sort();
+
+        Ok(result)
+    }
+
+    pub fn default_registry(&self) -> Result<String, Error> {
+        match fs::read_to_string(self.path.join(DEFAULT_FILE_NAME)) {
+            Ok(name) => Ok(String::from(name.trim())),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(String::from(DEFAULT_REGISTRY)),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn set_default_registry(&self, name: &str) -> Result<(), Error> {
+        let path = self.registry_path(name)?;
+        if !is_registry(&path) {
+            return Err(Error::NoSuchRegistry(String::from(name)));
+        }
+
+        fs::write(self.path.join(DEFAULT_FILE_NAME), name)?;
+
+        Ok(())
+    }
+
+    pub fn reset_default_registry(&self) -> Result<(), Error> {
+        match fs::remove_file(self.path.join(DEFAULT_FILE_NAME)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+const DEFAULT_FILE_NAME: &str = ".default";
+
+fn is_registry(path: &Path) -> bool {
+    if is_sqlite(path) {
+        return SqliteStorage::peek_header(path)
+            .and_then(|header_region| RegistryHeader::read(&mut header_region.as_slice()))
+            .is_ok();
+    }
+
+    File::open(path)
+        .map_err(Error::from)
+        .and_then(|mut file| RegistryHeader::read(&mut file))
+        .is_ok()
+}
@@ -1,49 +1,190 @@
-use std::{fs::File, io::{Write, self, Read}};
+use std::io::{Write, self, Read};
 
+use crate::errors::Error;
+
+use super::kdf_parameters::KdfParameters;
+
+#[derive(Clone)]
 pub struct RegistryHeader {
     pub version: i32,
-    pub name: [u8; 256],
+    pub flags: i32,
+    pub name: String,
     pub key_type: i32,
+    pub kdf: KdfParameters,
     pub public_key_size: i32,
     pub private_key_size: i32,
 }
 
+pub const REGISTRY_MAGIC: [u8; 8] = *b"ENIGMATC";
+pub const REGISTRY_VERSION: i32 = 4;
+/// Last version whose header region is not signed. Such registries are read until migrated.
+pub const UNSIGNED_REGISTRY_VERSION: i32 = 3;
+
+pub const FLAG_AUTHENTICATED: i32 = 1;
+pub const FLAG_PRIVATE_METADATA: i32 = 2;
+pub const FLAG_TEAM: i32 = 4;
+
 impl RegistryHeader {
-    pub fn read(file: &mut File) -> Result<Self, io::Error> {
-        let mut u32_buffer = [0u8; 4];
-        let mut name = [0u8; 256];
+    /// Whether the header region ends with a signature made with the registry private key.
+    pub fn signed(&self) -> bool {
+        self.version >= REGISTRY_VERSION
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self, Error> {
+        let mut reader = ChecksumReader {
+            inner: reader,
+            hasher: crc32fast::Hasher::new(),
+        };
+
+        let mut prefix = [0u8; 4];
+        reader.read_exact(prefix.as_mut_slice())?;
+
+        if prefix != REGISTRY_MAGIC[0..4] {
+            return match i32::from_le_bytes(prefix) {
+                version @ (1 | 2) => Self::read_legacy(&mut reader, version),
+                _ => Err(not_registry()),
+            };
+        }
 
-        file.read_exact(u32_buffer.as_mut_slice())?;
-        let version = i32::from_le_bytes(u32_buffer);
+        let mut magic = [0u8; 4];
+        reader.read_exact(magic.as_mut_slice())?;
+        if magic != REGISTRY_MAGIC[4..8] {
+            return Err(not_registry());
+        }
 
-        file.read_exact(name.as_mut_slice())?;
+        let version = read_i32(&mut reader)?;
+        if version != REGISTRY_VERSION && version != UNSIGNED_REGISTRY_VERSION {
+            return Err(Error::UnsupportedVersion(version as i64));
+        }
+
+        let flags = read_i32(&mut reader)?;
+        let key_type = read_i32(&mut reader)?;
+
+        let kdf = KdfParameters {
+            kdf_type: read_i32(&mut reader)?,
+            memory: read_u32(&mut reader)?,
+            iterations: read_u32(&mut reader)?,
+            parallelism: read_u32(&mut reader)?,
+            salt: read_bytes_array::<16>(&mut reader)?,
+        };
 
-        file.read_exact(u32_buffer.as_mut_slice())?;
-        let key_type = i32::from_le_bytes(u32_buffer);
+        let name_size = read_i32(&mut reader)?;
+        if !(0..=MAX_NAME_SIZE).contains(&name_size) {
+            return Err(Error::InvalidData(String::from("Registry header name is malformed")));
+        }
+        let mut name = vec![0u8; name_size as usize];
+        reader.read_exact(name.as_mut_slice())?;
+        let name = String::from_utf8(name)
+            .map_err(|_| Error::InvalidData(String::from("Registry header name is malformed")))?;
 
-        file.read_exact(u32_buffer.as_mut_slice())?;
-        let public_key_size = i32::from_le_bytes(u32_buffer);
+        let public_key_size = read_i32(&mut reader)?;
+        let private_key_size = read_i32(&mut reader)?;
 
-        file.read_exact(u32_buffer.as_mut_slice())?;
-        let private_key_size = i32::from_le_bytes(u32_buffer);
+        let checksum = reader.hasher.clone().finalize();
+        if read_u32(reader.inner)? != checksum {
+            return Err(Error::InvalidData(String::from("Registry header checksum mismatch")));
+        }
 
         let result = Self {
             version,
+            flags,
             name,
             key_type,
+            kdf,
             public_key_size,
             private_key_size,
         };
-        
+
         Ok(result)
     }
 
-    pub fn write(&self, file: &mut File) -> Result<(), io::Error> {
-        file.write(&self.version.to_le_bytes())?;
-        file.write(&self.name)?;
-        file.write(&self.key_type.to_le_bytes())?;
-        file.write(&self.public_key_size.to_le_bytes())?;
-        file.write(&self.private_key_size.to_le_bytes())?;
+    fn read_legacy(reader: &mut impl Read, version: i32) -> Result<Self, Error> {
+        let name = read_bytes_array::<256>(reader)?;
+        let name_size = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let name = String::from_utf8_lossy(&name[..name_size]).into_owned();
+
+        let key_type = read_i32(reader)?;
+        let public_key_size = read_i32(reader)?;
+        let private_key_size = read_i32(reader)?;
+
+        let flags = if version >= 2 {
+            read_i32(reader)?
+        }
+        else {
+            0
+        };
+
+        let result = Self {
+            version,
+            flags,
+            name,
+            key_type,
+            kdf: KdfParameters::none(),
+            public_key_size,
+            private_key_size,
+        };
+
+        Ok(result)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), Error> {
+        if self.version != REGISTRY_VERSION {
+            return Err(Error::UnsupportedVersion(self.version as i64));
+        }
+
+        let name = self.name.as_bytes();
+
+        let mut buffer = Vec::new();
+        buffer.write_all(&REGISTRY_MAGIC)?;
+        buffer.write_all(&self.version.to_le_bytes())?;
+        buffer.write_all(&self.flags.to_le_bytes())?;
+        buffer.write_all(&self.key_type.to_le_bytes())?;
+        buffer.write_all(&self.kdf.kdf_type.to_le_bytes())?;
+        buffer.write_all(&self.kdf.memory.to_le_bytes())?;
+        buffer.write_all(&self.kdf.iterations.to_le_bytes())?;
+        buffer.write_all(&self.kdf.parallelism.to_le_bytes())?;
+        buffer.write_all(&self.kdf.salt)?;
+        buffer.write_all(&(name.len() as i32).to_le_bytes())?;
+        buffer.write_all(name)?;
+        buffer.write_all(&self.public_key_size.to_le_bytes())?;
+        buffer.write_all(&self.private_key_size.to_le_bytes())?;
+        buffer.write_all(&crc32fast::hash(&buffer).to_le_bytes())?;
+
+        writer.write_all(&buffer)?;
+
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+const MAX_NAME_SIZE: i32 = 256;
+
+fn not_registry() -> Error {
+    Error::InvalidData(String::from("File is not an enigmatic registry"))
+}
+
+struct ChecksumReader<'a, R: Read> {
+    inner: &'a mut R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> Read for ChecksumReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.hasher.update(&buf[..count]);
+        Ok(count)
+    }
+}
+
+fn read_i32(reader: &mut impl Read) -> Result<i32, io::Error> {
+    Ok(i32::from_le_bytes(read_bytes_array::<4>(reader)?))
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, io::Error> {
+    Ok(u32::from_le_bytes(read_bytes_array::<4>(reader)?))
+}
+
+fn read_bytes_array<const COUNT: usize>(reader: &mut impl Read) -> Result<[u8; COUNT], io::Error> {
+    let mut buffer = [0u8; COUNT];
+    reader.read_exact(buffer.as_mut_slice())?;
+    Ok(buffer)
+}
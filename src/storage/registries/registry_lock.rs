@@ -0,0 +1,53 @@
+use std::{fs::File, path::Path, thread, time::{Duration, Instant}};
+
+use crate::errors::Error;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+pub struct RegistryLock {
+    _file: File,
+    pub mode: LockMode,
+}
+
+impl RegistryLock {
+    pub fn acquire(path: &Path, mode: LockMode, timeout: Duration) -> Result<Self, Error> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let started = Instant::now();
+        loop {
+            let result = match mode {
+                LockMode::Shared => file.try_lock_shared(),
+                LockMode::Exclusive => file.try_lock(),
+            };
+
+            match result {
+                Ok(()) => break,
+                Err(std::fs::TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    thread::sleep(LOCK_POLL_INTERVAL);
+                },
+                Err(std::fs::TryLockError::WouldBlock) => {
+                    return Err(Error::RegistryLocked);
+                },
+                Err(std::fs::TryLockError::Error(error)) => return Err(error.into()),
+            }
+        }
+
+        let result = Self {
+            _file: file,
+            mode,
+        };
+
+        Ok(result)
+    }
+}
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
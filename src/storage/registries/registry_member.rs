@@ -0,0 +1,137 @@
+use std::io::{self, Read};
+
+use ecies::PublicKey;
+
+use crate::errors::Error;
+
+/// Teammate whose public key every secret data key is wrapped to.
+///
+/// Removed members are kept with their removal time, so secrets they could read are known.
+#[derive(Clone)]
+pub struct RegistryMember {
+    pub name: String,
+    pub public_key: Vec<u8>,
+    pub added: u128,
+    pub removed: Option<u128>,
+    /// Secrets readable by the member must be rotated after its removal.
+    pub rotate: bool,
+}
+
+impl RegistryMember {
+    pub fn active(&self) -> bool {
+        self.removed.is_none()
+    }
+
+    /// Parses a public key in hex as printed by [`RegistryMember::format_public_key`].
+    pub fn parse_public_key(public_key: &str) -> Result<Vec<u8>, Error> {
+        let invalid = || Error::InvalidInput(format!("Invalid member public key {}", public_key));
+
+        let bytes = (0..public_key.len())
+            .step_by(2)
+            .map(|index| public_key.get(index..index + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        PublicKey::parse_slice(&bytes, None)
+            .map(|key| key.serialize_compressed().to_vec())
+            .map_err(|_| invalid())
+    }
+
+    pub fn format_public_key(public_key: &[u8]) -> String {
+        public_key.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+/// Encodes the member table stored after the registry keys: size, members and crc32.
+pub fn write_members(members: &[RegistryMember]) -> Vec<u8> {
+    let mut table = Vec::new();
+    table.extend_from_slice(&(members.len() as i32).to_le_bytes());
+
+    for member in members {
+        write_bytes(&mut table, member.name.as_bytes());
+        write_bytes(&mut table, &member.public_key);
+        table.extend_from_slice(&member.added.to_le_bytes());
+        table.extend_from_slice(&member.removed.unwrap_or(0).to_le_bytes());
+        table.push(member.rotate as u8);
+    }
+
+    let mut result = Vec::with_capacity(table.len() + 8);
+    result.extend_from_slice(&(table.len() as i32).to_le_bytes());
+    result.extend_from_slice(&table);
+    result.extend_from_slice(&crc32fast::hash(&table).to_le_bytes());
+
+    result
+}
+
+pub fn read_members(reader: &mut impl Read) -> Result<Vec<RegistryMember>, Error> {
+    let size = read_i32(reader)?;
+    if !(4..=MAX_TABLE_SIZE).contains(&size) {
+        return Err(malformed());
+    }
+
+    let mut table = vec![0u8; size as usize];
+    reader.read_exact(&mut table)?;
+
+    if read_array::<4>(reader)? != crc32fast::hash(&table).to_le_bytes() {
+        return Err(Error::InvalidData(String::from("Registry member table checksum mismatch")));
+    }
+
+    let mut reader = table.as_slice();
+    let count = read_i32(&mut reader).map_err(|_| malformed())?;
+
+    let mut result = Vec::new();
+    for _ in 0..count {
+        let name = String::from_utf8(read_bytes(&mut reader)?).map_err(|_| malformed())?;
+        let public_key = read_bytes(&mut reader)?;
+        let added = u128::from_le_bytes(read_array(&mut reader).map_err(|_| malformed())?);
+        let removed = u128::from_le_bytes(read_array(&mut reader).map_err(|_| malformed())?);
+        let rotate = read_array::<1>(&mut reader).map_err(|_| malformed())?[0] != 0;
+
+        result.push(RegistryMember {
+            name,
+            public_key,
+            added,
+            removed: (removed != 0).then_some(removed),
+            rotate,
+        });
+    }
+
+    if !reader.is_empty() {
+        return Err(malformed());
+    }
+
+    Ok(result)
+}
+
+const MAX_TABLE_SIZE: i32 = 1024 * 1024;
+
+fn malformed() -> Error {
+    Error::InvalidData(String::from("Registry member table is malformed"))
+}
+
+fn write_bytes(writer: &mut Vec<u8>, data: &[u8]) {
+    writer.extend_from_slice(&(data.len() as i32).to_le_bytes());
+    writer.extend_from_slice(data);
+}
+
+fn read_bytes(reader: &mut &[u8]) -> Result<Vec<u8>, Error> {
+    let size = read_i32(reader).map_err(|_| malformed())?;
+    if size < 0 || size as usize > reader.len() {
+        return Err(malformed());
+    }
+
+    let (data, rest) = reader.split_at(size as usize);
+    *reader = rest;
+
+    Ok(data.to_vec())
+}
+
+fn read_i32(reader: &mut impl Read) -> Result<i32, io::Error> {
+    Ok(i32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_array<const COUNT: usize>(reader: &mut impl Read) -> Result<[u8; COUNT], io::Error> {
+    let mut buffer = [0u8; COUNT];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
@@ -1,196 +1,689 @@
-use std::{str, path::PathBuf, fs::{self, File}, io::{Write, self, Read, Seek, SeekFrom}};
+use std::{path::{Path, PathBuf}, time::Duration};
 
 use ecies::{encrypt, decrypt, utils::generate_keypair};
+use sha3::{Digest, Sha3_256};
+use zeroize::{Zeroize, Zeroizing};
 
-use crate::storage::entries::EntryOperationDto;
+use crate::{errors::Error, storage::entries::EntryOperationDto};
 
-use super::registry_header::RegistryHeader;
+use super::{registry_header::{RegistryHeader, REGISTRY_VERSION, FLAG_AUTHENTICATED, FLAG_PRIVATE_METADATA, FLAG_TEAM}, header_signature::{sign_region, verify_region, SIGNATURE_SIZE}, registry_lock::LockMode, kdf_parameters::{KdfParameters, KDF_ARGON2ID, DEFAULT_KDF_MEMORY, DEFAULT_KDF_ITERATIONS}, key_wrapping::{wrap_private_key, unwrap_private_key}, private_key::PrivateKey, metadata_cipher::MetadataCipher, secret_padding::{SecretPadding, PADDED_SECRET_TAG, unpad}, secret_envelope::{self, ENVELOPE_TAG}, registry_member::{RegistryMember, write_members, read_members}, registry_storage::RegistryStorage, file_storage::FileStorage, sqlite_storage::{SqliteStorage, is_sqlite}};
 
+/// An open registry holding its key pair header and operation log in a `RegistryStorage`.
 pub struct RegistryRepository {
-    file: File,
+    storage: Box<dyn RegistryStorage>,
+    header: RegistryHeader,
     pub name: String,
     public_key: Vec<u8>,
-    private_key: Option<Vec<u8>>,
+    encrypted_private_key: Vec<u8>,
+    private_key: Option<PrivateKey>,
+    metadata: Option<MetadataCipher>,
+    padding: SecretPadding,
+    members: Vec<RegistryMember>,
+    member_key: Option<(Vec<u8>, PrivateKey)>,
 }
 
 impl RegistryRepository {
-    pub fn init(path: &PathBuf, name: &str, password: &str) -> Result<Self, io::Error> {
-        let (private_key, public_key) = generate_keypair();
-        let public_key = public_key.serialize_compressed().to_vec();
-        let private_key = private_key.serialize().to_vec();
-
-        fs::create_dir_all(path)?;
-    
-        let file_path = path.join(FILE_NAME);
+    /// Creates a new registry file at `path`.
+    pub fn init(
+        path: &Path, 
+        name: &str, 
+        password: &str, 
+        authenticated: bool, 
+        private_metadata: bool, 
+        kdf: KdfParameters,
+    ) -> Result<Self, Error> {
+        Self::init_with(Box::new(FileStorage::create(path)?), name, password, authenticated, private_metadata, kdf)
+    }
 
-        if file_path.exists() {
-            panic!("Can not initialize existing registry");
-        }
-    
-        let encrypted_private_key = simplecrypt::encrypt(&private_key, password.as_bytes());
+    /// Creates a new registry in the empty `storage`.
+    /// With `private_metadata` entry names and descriptions are encrypted as well.
+    pub fn init_with(
+        mut storage: Box<dyn RegistryStorage>, 
+        name: &str, 
+        password: &str, 
+        authenticated: bool, 
+        private_metadata: bool, 
+        kdf: KdfParameters,
+    ) -> Result<Self, Error> {
+        let (private_key, public_key) = generate_key_pair();
+
+        let encrypted_private_key = match wrap_private_key(&private_key, &public_key, REGISTRY_VERSION, password, &kdf) {
+            Ok(encrypted_private_key) => encrypted_private_key,
+            Err(error) => {
+                storage.remove()?;
+                return Err(error);
+            },
+        };
     
         let name: String = name.chars().take(64).collect();
     
-        let mut name_bytes = [0u8; 256];
-        let bytes = name.as_bytes();
-        name_bytes[..bytes.len()].clone_from_slice(bytes);
-    
+        let mut flags = if authenticated { FLAG_AUTHENTICATED } else { 0 };
+        if private_metadata {
+            flags |= FLAG_PRIVATE_METADATA;
+        }
+
+        let metadata = private_metadata.then(|| MetadataCipher::new(&private_key));
+
         let header = RegistryHeader {
-            version: 1,
-            name: name_bytes,
+            version: REGISTRY_VERSION,
+            flags,
+            name: name.clone(),
             key_type: 1,
+            kdf,
             public_key_size: public_key.len() as i32,
             private_key_size: encrypted_private_key.len() as i32,
         };
-    
-        let mut file = File::create(file_path)?;
-        header.write(&mut file)?;
-        file.write(&public_key)?;
-        file.write(&encrypted_private_key)?;
+
+        let header_region = header_region(&header, &public_key, &encrypted_private_key, &[], &private_key)?;
+        storage.write_header(&header_region)?;
+        storage.rewind()?;
 
         let result = Self {
-            file,
+            storage,
+            header,
             name,
             public_key,
+            encrypted_private_key,
             private_key: Some(private_key),
+            metadata,
+            padding: SecretPadding::default(),
+            members: Vec::new(),
+            member_key: None,
         };
 
         Ok(result)
     }
 
-    pub fn open(path: &PathBuf) -> Result<Self, io::Error> {
-        let mut file = File::options()
-            .read(true)
-            .write(true)
-            .open(path.join(FILE_NAME))?;
+    /// Opens the registry at `path` without unlocking its private key, waiting up to `lock_timeout` for the lock.
+    /// SQLite databases are detected by their magic, anything else is opened as a registry file.
+    pub fn open(path: &Path, lock_mode: LockMode, lock_timeout: Duration) -> Result<Self, Error> {
+        let storage: Box<dyn RegistryStorage> = if is_sqlite(path) {
+            Box::new(SqliteStorage::open(path, lock_mode, lock_timeout)?)
+        }
+        else {
+            Box::new(FileStorage::open(path, lock_mode, lock_timeout)?)
+        };
+
+        Self::with_storage(storage)
+    }
+
+    /// Opens the registry kept in `storage` without unlocking its private key.
+    pub fn with_storage(mut storage: Box<dyn RegistryStorage>) -> Result<Self, Error> {
+        let header_region = storage.read_header()?;
+        let mut reader = header_region.as_slice();
+
+        let header = RegistryHeader::read(&mut reader)?;
+
+        let (public_key, reader) = split_region(reader, header.public_key_size)?;
+        let (encrypted_private_key, mut reader) = split_region(reader, header.private_key_size)?;
+
+        let members = if header.flags & FLAG_TEAM != 0 {
+            read_members(&mut reader)?
+        }
+        else {
+            Vec::new()
+        };
+
+        if header.signed() {
+            let signed_size = header_region.len() - reader.len();
+            let (signature, rest) = split_region(reader, SIGNATURE_SIZE as i32)?;
+
+            if !verify_region(&header_region[..signed_size], signature, public_key) {
+                return Err(Error::InvalidData(String::from("Registry header signature mismatch")));
+            }
+
+            reader = rest;
+        }
 
-        let header = RegistryHeader::read(&mut file)?;
+        if !reader.is_empty() {
+            return Err(Error::InvalidData(String::from("Registry header region is malformed")));
+        }
 
-        let mut public_key = vec![0; header.public_key_size as usize];
-        file.read_exact(public_key.as_mut_slice())?;
+        storage.rewind()?;
 
-        file.seek(SeekFrom::Current(header.private_key_size as i64))?;
-        
         let result = Self {
-            file,
-            name: String::from(str::from_utf8(&header.name).unwrap()),
-            public_key,
+            storage,
+            name: header.name.clone(),
+            header,
+            public_key: public_key.to_vec(),
+            encrypted_private_key: encrypted_private_key.to_vec(),
             private_key: None,
+            metadata: None,
+            padding: SecretPadding::default(),
+            members,
+            member_key: None,
         };
 
         Ok(result)
     }
 
-    pub fn open_decrypt(path: &PathBuf, password: &str) -> Result<Self, io::Error> {
-        let mut file = File::options()
-            .read(true)
-            .write(true)
-            .open(path.join(FILE_NAME))?;
+    pub(crate) fn stage(path: &Path, header_region: &[u8]) -> Result<Self, Error> {
+        Self::with_storage(Box::new(FileStorage::stage(path, header_region)?))
+    }
+
+    /// Opens the registry at `path` and unlocks it with `password`.
+    pub fn open_decrypt(path: &Path, password: &str, lock_mode: LockMode, lock_timeout: Duration) -> Result<Self, Error> {
+        let mut result = Self::open(path, lock_mode, lock_timeout)?;
+        result.unlock(password)?;
+        Ok(result)
+    }
+
+    /// Derives the key from `password` and decrypts the private key.
+    pub fn unlock(&mut self, password: &str) -> Result<(), Error> {
+        let private_key = PrivateKey::new(unwrap_private_key(
+            &self.encrypted_private_key, 
+            &self.public_key, 
+            self.header.version, 
+            password, 
+            &self.header.kdf,
+        )?);
+
+        if self.metadata_private() {
+            self.metadata = Some(MetadataCipher::new(&private_key));
+        }
+        self.private_key = Some(private_key);
+
+        Ok(())
+    }
+
+    /// Unlocks this registry with the private key of unlocked `other` sharing the same key pair.
+    pub fn unlock_with(&mut self, other: &Self) {
+        if self.public_key != other.public_key || self.unlocked() {
+            return;
+        }
+
+        if let Some(private_key) = other.private_key.clone() {
+            if self.metadata_private() {
+                self.metadata = Some(MetadataCipher::new(&private_key));
+            }
+            self.private_key = Some(private_key);
+        }
+    }
+
+    /// Unlocks secrets with the private key of unlocked `identity`, the registry of an active member.
+    /// Members only unwrap data keys, so the registry stays unable to tag operations or read private metadata.
+    pub fn unlock_as(&mut self, identity: &Self) -> Result<(), Error> {
+        let private_key = identity.private_key.clone().ok_or_else(|| Error::PasswordRequired(
+            String::from("Identity registry must be unlocked"),
+        ))?;
 
-        let header = RegistryHeader::read(&mut file)?;
+        if !self.members.iter().any(|member| member.active() && member.public_key == identity.public_key) {
+            return Err(Error::NotPermitted(format!("Registry [{}] is not a member of registry [{}]", identity.name, self.name)));
+        }
+
+        self.member_key = Some((identity.public_key.clone(), private_key));
+
+        Ok(())
+    }
+
+    pub fn unlocked(&self) -> bool {
+        self.private_key.is_some()
+    }
+
+    pub fn authentication_required(&self) -> bool {
+        self.header.flags & FLAG_AUTHENTICATED != 0
+    }
+
+    /// Whether entry names and descriptions are encrypted, so reading the log requires the password.
+    pub fn metadata_private(&self) -> bool {
+        self.header.flags & FLAG_PRIVATE_METADATA != 0
+    }
+
+    pub(crate) fn authentication_tag(&self, digest: &[u8]) -> Option<[u8; 32]> {
+        let private_key = self.private_key.as_ref()?;
 
-        let mut public_key = vec![0; header.public_key_size as usize];
-        file.read_exact(public_key.as_mut_slice())?;
+        let mut key_hasher = Sha3_256::new();
+        key_hasher.update(AUTHENTICATION_CONTEXT);
+        key_hasher.update(&private_key[..]);
+        let mut authentication_key = key_hasher.finalize();
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(authentication_key);
+        hasher.update(digest);
+
+        authentication_key.as_mut_slice().zeroize();
+
+        Some(hasher.finalize().into())
+    }
+
+    /// Storage backend name, `file`, `memory` or `sqlite`.
+    pub fn storage_kind(&self) -> &'static str {
+        self.storage.kind()
+    }
+
+    pub fn size(&self) -> Result<u64, Error> {
+        self.storage.size()
+    }
+
+    pub fn remove(self) -> Result<(), Error> {
+        self.ensure_writable()?;
+
+        self.storage.remove()
+    }
+
+    pub fn version(&self) -> i32 {
+        self.header.version
+    }
+
+    /// Whether the registry is in the current format, see [`RegistryRepository::migrate`].
+    pub fn up_to_date(&self) -> bool {
+        self.header.version == REGISTRY_VERSION
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Header region and log in the file format, as stored in backup bundles.
+    pub(crate) fn regions(&mut self) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        Ok((self.storage.read_header()?, self.storage.read_log()?))
+    }
+
+    pub(crate) fn write_log(&mut self, log: &[u8]) -> Result<(), Error> {
+        self.ensure_writable()?;
+
+        self.storage.append_log(log)
+    }
+
+    pub(crate) fn install(self, path: &Path) -> Result<(), Error> {
+        self.storage.install(path)
+    }
+
+    pub(crate) fn discard(self) -> Result<(), Error> {
+        self.storage.discard()
+    }
+
+    pub(crate) fn rewrite(&self) -> Result<Self, Error> {
+        let storage = self.storage.create_replacement(&self.storage.read_header()?)?;
 
-        let mut private_key = vec![0; header.private_key_size as usize];
-        file.read_exact(private_key.as_mut_slice())?;
-        
         let result = Self {
-            file,
-            name: String::from(str::from_utf8(&header.name).unwrap()),
-            public_key,
-            private_key: Some(simplecrypt::decrypt(&private_key, password.as_bytes()).unwrap()),
+            storage,
+            header: self.header.clone(),
+            name: self.name.clone(),
+            public_key: self.public_key.clone(),
+            encrypted_private_key: self.encrypted_private_key.clone(),
+            private_key: self.private_key.clone(),
+            metadata: self.metadata.clone(),
+            padding: self.padding.clone(),
+            members: self.members.clone(),
+            member_key: self.member_key.clone(),
         };
 
         Ok(result)
     }
 
-    pub fn write_operation(&mut self, operation: &EntryOperationDto) -> Result<(), io::Error> {
-        match operation {
-            EntryOperationDto::Add { hash, timestamp, name, description, secret } => {
-                self.file.write(&1i32.to_le_bytes())?;
-                self.file.write(hash)?;
-                self.file.write(&timestamp.to_le_bytes())?;
+    fn rewrite_header(&mut self, header: RegistryHeader, encrypted_private_key: Vec<u8>) -> Result<Self, Error> {
+        let private_key = self.private_key.as_ref().ok_or_else(|| Error::PasswordRequired(
+            String::from("Registry must be unlocked to rewrite its header"),
+        ))?;
 
-                write_string(&mut self.file, Some(name))?;
-                write_string(&mut self.file, Some(description))?;
-                write_bytes(&mut self.file, Some(secret))?;
-            },
-            EntryOperationDto::Set { hash, timestamp, src_name, dst_name, dst_description, dst_secret } => {
-                self.file.write(&2i32.to_le_bytes())?;
-                self.file.write(hash)?;
-                self.file.write(&timestamp.to_le_bytes())?;
-
-                write_string(&mut self.file, Some(src_name))?;
-                write_string(&mut self.file, dst_name.as_ref().map(|o| o.as_str()))?;
-                write_string(&mut self.file, dst_description.as_ref().map(|o| o.as_str()))?;
-                write_bytes(&mut self.file, dst_secret.as_ref().map(|o| o.as_slice()))?;
-            },
-            EntryOperationDto::Del { hash, timestamp, name } => {
-                self.file.write(&3i32.to_le_bytes())?;
-                self.file.write(hash)?;
-                self.file.write(&timestamp.to_le_bytes())?;
+        let header_region = header_region(&header, &self.public_key, &encrypted_private_key, &self.members, private_key)?;
 
-                write_string(&mut self.file, Some(name))?;
-            },
+        let mut storage = self.storage.create_replacement(&header_region)?;
+        storage.append_log(&self.storage.read_log()?)?;
+
+        let result = Self {
+            storage,
+            name: header.name.clone(),
+            header,
+            public_key: self.public_key.clone(),
+            encrypted_private_key,
+            private_key: self.private_key.clone(),
+            metadata: self.metadata.clone(),
+            padding: self.padding.clone(),
+            members: self.members.clone(),
+            member_key: self.member_key.clone(),
+        };
+
+        Ok(result)
+    }
+
+    pub(crate) fn rekey(&self, password: &str) -> Result<Self, Error> {
+        let (private_key, public_key) = generate_key_pair();
+
+        let kdf = if self.header.kdf.kdf_type == KDF_ARGON2ID {
+            KdfParameters::argon2id(self.header.kdf.memory, self.header.kdf.iterations)
         }
+        else {
+            KdfParameters::argon2id(DEFAULT_KDF_MEMORY, DEFAULT_KDF_ITERATIONS)
+        };
+
+        let encrypted_private_key = wrap_private_key(&private_key, &public_key, REGISTRY_VERSION, password, &kdf)?;
+
+        let header = RegistryHeader {
+            version: REGISTRY_VERSION,
+            kdf,
+            public_key_size: public_key.len() as i32,
+            private_key_size: encrypted_private_key.len() as i32,
+            ..self.header.clone()
+        };
+
+        let header_region = header_region(&header, &public_key, &encrypted_private_key, &self.members, &private_key)?;
+
+        let metadata = self.metadata_private().then(|| MetadataCipher::new(&private_key));
+
+        let result = Self {
+            storage: self.storage.create_replacement(&header_region)?,
+            name: header.name.clone(),
+            header,
+            public_key,
+            encrypted_private_key,
+            private_key: Some(private_key),
+            metadata,
+            padding: self.padding.clone(),
+            members: self.members.clone(),
+            member_key: None,
+        };
+
+        Ok(result)
+    }
+
+    pub(crate) fn rewind(&mut self) -> Result<(), Error> {
+        self.storage.rewind()
+    }
+
+    pub fn kdf(&self) -> &KdfParameters {
+        &self.header.kdf
+    }
+
+    pub fn change_password(mut self, password: &str, kdf: KdfParameters) -> Result<(), Error> {
+        self.ensure_writable()?;
+
+        let private_key = self.private_key.as_ref().ok_or_else(|| Error::PasswordRequired(
+            String::from("Registry must be unlocked to change password"),
+        ))?;
+
+        let encrypted_private_key = wrap_private_key(private_key, &self.public_key, REGISTRY_VERSION, password, &kdf)?;
+
+        let header = RegistryHeader {
+            version: REGISTRY_VERSION,
+            kdf,
+            private_key_size: encrypted_private_key.len() as i32,
+            ..self.header.clone()
+        };
+
+        let replacement = self.rewrite_header(header, encrypted_private_key)?;
+
+        self.replace(replacement, false)?;
 
         Ok(())
     }
 
-    pub fn read_operation(&mut self) -> Result<Option<EntryOperationDto>, io::Error> {
-        if let Some(op_code) = read_i32_option(&mut self.file)? {
-            let result = match op_code {
-                1 => {
-                    EntryOperationDto::Add { 
-                        hash: read_bytes_array::<64>(&mut self.file)?, 
-                        timestamp: read_u128(&mut self.file)?,
-                        name: read_string(&mut self.file)?.unwrap(), 
-                        description: read_string(&mut self.file)?.unwrap(), 
-                        secret: read_bytes(&mut self.file)?.unwrap(),
-                    }
-                },
-                2 => {
-                    EntryOperationDto::Set { 
-                        hash: read_bytes_array::<64>(&mut self.file)?, 
-                        timestamp: read_u128(&mut self.file)?,
-                        src_name: read_string(&mut self.file)?.unwrap(), 
-                        dst_name: read_string(&mut self.file)?, 
-                        dst_description: read_string(&mut self.file)?, 
-                        dst_secret: read_bytes(&mut self.file)?,
-                    }
-                },
-                3 => {
-                    EntryOperationDto::Del { 
-                        hash: read_bytes_array::<64>(&mut self.file)?, 
-                        timestamp: read_u128(&mut self.file)?,
-                        name: read_string(&mut self.file)?.unwrap(),
-                    }
-                },
-                _ => panic!("Invalid entry operation code"),
-            };
-    
-            Ok(Some(result))
+    /// Upgrades the registry to the current format keeping a backup. The private key is wrapped
+    /// again for the current version and the header signed with it, so `password` is required.
+    pub fn migrate(mut self, password: &str) -> Result<Option<PathBuf>, Error> {
+        self.ensure_writable()?;
+
+        if self.up_to_date() {
+            return Ok(None);
         }
-        else {
-            Ok(None)
+
+        self.unlock(password)?;
+
+        let private_key = self.private_key.as_ref().ok_or_else(|| Error::PasswordRequired(
+            String::from("Registry must be unlocked to migrate"),
+        ))?;
+        let encrypted_private_key = wrap_private_key(private_key, &self.public_key, REGISTRY_VERSION, password, &self.header.kdf)?;
+
+        let header = RegistryHeader {
+            version: REGISTRY_VERSION,
+            private_key_size: encrypted_private_key.len() as i32,
+            ..self.header.clone()
+        };
+
+        let replacement = self.rewrite_header(header, encrypted_private_key)?;
+
+        self.replace(replacement, true)
+    }
+
+    pub fn members(&self) -> &[RegistryMember] {
+        &self.members
+    }
+
+    /// Creates an empty replacement with the member table set to `members`, to be filled with re-encrypted operations.
+    pub(crate) fn with_members(&self, members: Vec<RegistryMember>) -> Result<Self, Error> {
+        self.ensure_up_to_date()?;
+
+        let private_key = self.private_key.clone().ok_or_else(|| Error::PasswordRequired(
+            String::from("Registry must be unlocked to manage members"),
+        ))?;
+
+        let header = RegistryHeader {
+            version: REGISTRY_VERSION,
+            flags: self.header.flags | FLAG_TEAM,
+            ..self.header.clone()
+        };
+
+        let header_region = header_region(&header, &self.public_key, &self.encrypted_private_key, &members, &private_key)?;
+
+        let result = Self {
+            storage: self.storage.create_replacement(&header_region)?,
+            name: header.name.clone(),
+            header,
+            public_key: self.public_key.clone(),
+            encrypted_private_key: self.encrypted_private_key.clone(),
+            private_key: Some(private_key),
+            metadata: self.metadata.clone(),
+            padding: self.padding.clone(),
+            members,
+            member_key: None,
+        };
+
+        Ok(result)
+    }
+
+    /// Rewrites every operation with entry names and descriptions encrypted or in plaintext, keeping a backup.
+    /// Returns `None` when the registry already is in the requested mode.
+    pub fn convert_metadata(mut self, private: bool) -> Result<Option<PathBuf>, Error> {
+        self.ensure_writable()?;
+
+        if self.metadata_private() == private {
+            return Ok(None);
+        }
+
+        self.ensure_up_to_date()?;
+
+        if private && self.members.iter().any(|member| member.active()) {
+            return Err(Error::NotPermitted(String::from("Members can not read private metadata, remove them first")));
+        }
+
+        let private_key = self.private_key.clone().ok_or_else(|| Error::PasswordRequired(
+            String::from("Registry must be unlocked to convert its metadata"),
+        ))?;
+
+        let header = RegistryHeader {
+            version: REGISTRY_VERSION,
+            flags: self.header.flags ^ FLAG_PRIVATE_METADATA,
+            ..self.header.clone()
+        };
+
+        let header_region = header_region(&header, &self.public_key, &self.encrypted_private_key, &self.members, &private_key)?;
+
+        let mut replacement = Self {
+            storage: self.storage.create_replacement(&header_region)?,
+            name: header.name.clone(),
+            header,
+            public_key: self.public_key.clone(),
+            encrypted_private_key: self.encrypted_private_key.clone(),
+            metadata: private.then(|| MetadataCipher::new(&private_key)),
+            private_key: Some(private_key),
+            padding: self.padding.clone(),
+            members: self.members.clone(),
+            member_key: None,
+        };
+
+        self.rewind()?;
+        while let Some(operation) = self.read_operation()? {
+            replacement.write_operation(&operation)?;
+        }
+
+        if let Some(offset) = self.torn_tail() {
+            replacement.discard()?;
+
+            return Err(Error::CorruptLog {
+                offset,
+                reason: String::from("torn record, repair it before conversion"),
+            });
         }
+
+        self.replace(replacement, true)
     }
 
+    /// Swaps this registry with `replacement`, copying it into this storage kind first when they differ.
+    pub(crate) fn replace(self, replacement: Self, archive: bool) -> Result<Option<PathBuf>, Error> {
+        self.ensure_writable()?;
+
+        let mut replacement = replacement.storage;
 
-    // pub fn decrypted(&self) -> bool {
-    //     self.private_key.is_some()
-    // }
+        if replacement.kind() != self.storage.kind() {
+            let header_region = replacement.read_header()?;
+            let log = replacement.read_log()?;
 
-    pub fn encrypt(&self, data: &[u8]) -> Vec<u8> {
-        encrypt(&self.public_key, data).unwrap()
+            replacement.discard()?;
+
+            replacement = self.storage.create_replacement(&header_region)?;
+            replacement.append_log(&log)?;
+        }
+
+        self.storage.replace(replacement, archive)
+    }
+
+    pub(crate) fn position(&mut self) -> Result<u64, Error> {
+        self.storage.position()
+    }
+
+    pub fn torn_tail(&self) -> Option<u64> {
+        self.storage.torn_tail()
     }
 
-    pub fn decrypt(&self, data: &[u8]) -> Option<Vec<u8>> {
+    /// Reads the rest of the log and returns the offset of a torn last operation, if any.
+    pub fn find_torn_tail(&mut self) -> Result<Option<u64>, Error> {
+        while self.read_operation()?.is_some() {}
+
+        Ok(self.torn_tail())
+    }
+
+    pub fn truncate_tail(&mut self) -> Result<u64, Error> {
+        self.ensure_writable()?;
+
+        self.storage.truncate_tail()
+    }
+
+    /// Appends `operation`, sealing its names and description when metadata is private.
+    pub(crate) fn write_operation(&mut self, operation: &EntryOperationDto) -> Result<(), Error> {
+        self.ensure_writable()?;
+
+        if !self.metadata_private() {
+            return self.storage.append_operation(operation);
+        }
+
+        let metadata = self.metadata.as_mut().ok_or_else(metadata_locked)?;
+        let operation = metadata.seal(operation, &self.public_key)?;
+
+        self.storage.append_operation(&operation)
+    }
+
+    /// Reads the next operation, opening its names and description when metadata is private.
+    pub(crate) fn read_operation(&mut self) -> Result<Option<EntryOperationDto>, Error> {
+        if !self.metadata_private() {
+            return self.storage.read_operation();
+        }
+
+        let (Some(metadata), Some(private_key)) = (self.metadata.as_mut(), self.private_key.as_ref()) else {
+            return Err(metadata_locked());
+        };
+
+        let offset = self.storage.position()?;
+
+        let Some(operation) = self.storage.read_operation()? else {
+            return Ok(None);
+        };
+
+        metadata.open(operation, private_key)
+            .map(Some)
+            .map_err(|reason| Error::CorruptLog {
+                offset,
+                reason: String::from(reason),
+            })
+    }
+
+    /// Rewriting the header keeps the wrapped private key, which is bound to the format version.
+    fn ensure_up_to_date(&self) -> Result<(), Error> {
+        if !self.up_to_date() {
+            return Err(Error::NotPermitted(String::from("Registry format is outdated, migrate it first")));
+        }
+
+        Ok(())
+    }
+
+    fn ensure_writable(&self) -> Result<(), Error> {
+        if !self.storage.writable() {
+            return Err(Error::NotPermitted(String::from("Registry is opened for reading only")));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        encrypt(&self.public_key, data)
+            .map_err(|_| Error::InvalidData(String::from("Registry public key is malformed")))
+    }
+
+    /// Scheme secrets encrypted by `encrypt_secret` are padded with.
+    pub fn padding(&self) -> &SecretPadding {
+        &self.padding
+    }
+
+    /// Sets the scheme secrets written through this repository are padded with. It is not stored in the registry.
+    pub fn set_padding(&mut self, padding: SecretPadding) {
+        self.padding = padding;
+    }
+
+    /// Encrypts entry `secret` padded with the registry padding scheme.
+    /// Secrets of registries with active members are sealed with a data key wrapped to each of them.
+    pub fn encrypt_secret(&self, secret: &[u8]) -> Result<Vec<u8>, Error> {
+        let padded = self.padding.pad(secret);
+
+        let recipients: Vec<&[u8]> = [self.public_key.as_slice()].into_iter()
+            .chain(self.members.iter().filter(|member| member.active()).map(|member| member.public_key.as_slice()))
+            .collect();
+
+        if recipients.len() > 1 {
+            if !self.header.signed() {
+                return Err(Error::NotPermitted(String::from("Member table of registry is not signed, migrate it first")));
+            }
+
+            return secret_envelope::seal(padded.as_deref().map_or(secret, |padded| padded.as_slice()), padded.is_some(), &recipients);
+        }
+
+        let Some(padded) = padded else {
+            return self.encrypt(secret);
+        };
+
+        let mut result = vec![PADDED_SECRET_TAG];
+        result.extend_from_slice(&self.encrypt(&padded)?);
+
+        Ok(result)
+    }
+
+    /// Decrypts entry secret encrypted by `encrypt_secret`, with the registry key or the key of the unlocking member.
+    pub fn decrypt_secret(&self, data: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        match data.split_first() {
+            Some((&PADDED_SECRET_TAG, sealed)) => unpad(&self.decrypt(sealed)?),
+            Some((&ENVELOPE_TAG, _)) => match (self.private_key.as_ref(), self.member_key.as_ref()) {
+                (Some(private_key), _) => secret_envelope::open(data, &self.public_key, private_key),
+                (None, Some((public_key, private_key))) => secret_envelope::open(data, public_key, private_key),
+                (None, None) => None,
+            },
+            _ => self.decrypt(data),
+        }
+    }
+
+    pub(crate) fn decrypt(&self, data: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
         if let Some(private_key) = self.private_key.as_ref() {
-            match decrypt(private_key, data) {
+            match decrypt(private_key, data).map(Zeroizing::new) {
                 Ok(result) => {
-                    if data.len() > 0 && result.len() == 0 {
+                    if !data.is_empty() && result.is_empty() {
                         None
                     }
                     else {
@@ -206,68 +699,50 @@ impl RegistryRepository {
     }
 }
 
-const FILE_NAME: &str = "registry";
+const AUTHENTICATION_CONTEXT: &[u8] = b"enigmatic/operation-authentication";
 
-fn write_i32(file: &mut File, data: i32) -> Result<(), io::Error> {
-    file.write(&data.to_le_bytes())?;
-    Ok(())
+fn metadata_locked() -> Error {
+    Error::PasswordRequired(String::from("Registry metadata is private, unlock it with password"))
 }
 
-fn read_i32_option(file: &mut File) -> Result<Option<i32>, io::Error> {
-    let mut buffer = [0u8; 4];
-    if file.read(buffer.as_mut_slice())? != 4 {
-        return Ok(None);
-    }
-    Ok(Some(i32::from_le_bytes(buffer)))
-}
+/// Generates a registry key pair, returning the private key and the compressed public key.
+fn generate_key_pair() -> (PrivateKey, Vec<u8>) {
+    let (secret_key, public_key) = generate_keypair();
 
+    let mut serialized = secret_key.serialize();
+    let private_key = PrivateKey::new(Zeroizing::new(serialized.to_vec()));
+    serialized.zeroize();
 
-fn read_i32(file: &mut File) -> Result<i32, io::Error> {
-    let mut buffer = [0u8; 4];
-    file.read_exact(buffer.as_mut_slice())?;
-    Ok(i32::from_le_bytes(buffer))
-}
-
-fn read_u128(file: &mut File) -> Result<u128, io::Error> {
-    let mut buffer = [0u8; 16];
-    file.read_exact(buffer.as_mut_slice())?;
-    Ok(u128::from_le_bytes(buffer))
+    (private_key, public_key.serialize_compressed().to_vec())
 }
 
-fn write_bytes(file: &mut File, data: Option<&[u8]>) -> Result<(), io::Error> {
-    if let Some(data) = data {
-        write_i32(file, data.len() as i32)?;
-        file.write(data)?;
+/// Serializes the header, the keys and the member table, signed with `private_key`.
+fn header_region(
+    header: &RegistryHeader, 
+    public_key: &[u8], 
+    encrypted_private_key: &[u8],
+    members: &[RegistryMember],
+    private_key: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    header.write(&mut result)?;
+    result.extend_from_slice(public_key);
+    result.extend_from_slice(encrypted_private_key);
+    if header.flags & FLAG_TEAM != 0 {
+        result.extend_from_slice(&write_members(members));
     }
-    else {
-        write_i32(file, -1)?;
+    if header.signed() {
+        let signature = sign_region(&result, private_key)?;
+        result.extend_from_slice(&signature);
     }
 
-    Ok(())
+    Ok(result)
 }
 
-fn read_bytes(file: &mut File) -> Result<Option<Vec<u8>>, io::Error> {
-    let size = read_i32(file)?;
-    if size == -1 {
-        return Ok(None);
+fn split_region(region: &[u8], size: i32) -> Result<(&[u8], &[u8]), Error> {
+    if size < 0 || size as usize > region.len() {
+        return Err(Error::InvalidData(String::from("Registry header region is malformed")));
     }
 
-    let mut data = vec![0; size as usize];
-    file.read_exact(data.as_mut_slice())?;
-
-    Ok(Some(data))
-}
-
-fn read_bytes_array<const COUNT: usize>(file: &mut File) -> Result<[u8; COUNT], io::Error> {
-    let mut buffer = [0u8; COUNT];
-    file.read_exact(buffer.as_mut_slice())?;
-    return Ok(buffer);
-}
-
-fn write_string(file: &mut File, data: Option<&str>) -> Result<(), io::Error> {
-    write_bytes(file, data.map(|x| x.as_bytes()))
-}
-
-fn read_string(file: &mut File) -> Result<Option<String>, io::Error> {
-    read_bytes(file).map(|r| r.map(|o| String::from_utf8(o).unwrap()))
+    Ok(region.split_at(size as usize))
 }
@@ -0,0 +1,136 @@
+use std::{fs, io, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::{errors::Error, storage::entries::EntryOperationDto};
+
+use super::operation_codec::{encode_frame, decode_log};
+
+/// Where a registry keeps its header region and operation log.
+///
+/// The header region is the serialized header followed by the public key and the wrapped private key.
+/// Operations are read sequentially from a cursor and only ever appended.
+pub trait RegistryStorage {
+    /// Short backend name, `file`, `memory` or `sqlite`.
+    fn kind(&self) -> &'static str;
+
+    /// Location of the registry, if it has one.
+    fn path(&self) -> Option<&Path>;
+
+    fn writable(&self) -> bool;
+
+    fn read_header(&self) -> Result<Vec<u8>, Error>;
+
+    /// Writes the header region of a newly created storage.
+    fn write_header(&mut self, header_region: &[u8]) -> Result<(), Error>;
+
+    /// Moves the cursor to the first operation.
+    fn rewind(&mut self) -> Result<(), Error>;
+
+    /// Cursor position reported in log errors, a byte offset or a row id depending on the backend.
+    fn position(&mut self) -> Result<u64, Error>;
+
+    /// Reads the operation at the cursor. Returns `None` at the end of the log or at a torn tail.
+    fn read_operation(&mut self) -> Result<Option<EntryOperationDto>, Error>;
+
+    /// Appends `operation` to the log and moves the cursor past it.
+    fn append_operation(&mut self, operation: &EntryOperationDto) -> Result<(), Error>;
+
+    fn size(&self) -> Result<u64, Error>;
+
+    /// Makes durable everything appended so far.
+    fn sync(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Creates an empty storage of the same kind next to this one, to be passed to `replace` later.
+    fn create_replacement(&self, header_region: &[u8]) -> Result<Box<dyn RegistryStorage>, Error>;
+
+    /// Atomically swaps this storage with `replacement` created by `create_replacement`.
+    /// Returns the archive path when `archive` is set and the backend keeps one.
+    fn replace(self: Box<Self>, replacement: Box<dyn RegistryStorage>, archive: bool) -> Result<Option<PathBuf>, Error>;
+
+    /// Moves a staged storage to `path`.
+    fn install(self: Box<Self>, path: &Path) -> Result<(), Error>;
+
+    /// Deletes a staged storage.
+    fn discard(self: Box<Self>) -> Result<(), Error>;
+
+    /// Deletes the registry.
+    fn remove(self: Box<Self>) -> Result<(), Error>;
+
+    /// Position of a partially written last operation found while reading.
+    fn torn_tail(&self) -> Option<u64> {
+        None
+    }
+
+    /// Drops the torn tail. Returns the number of bytes removed.
+    fn truncate_tail(&mut self) -> Result<u64, Error> {
+        Ok(0)
+    }
+
+    /// Whole log in the file format, as stored in backup bundles. Leaves the cursor at the end of the log.
+    fn read_log(&mut self) -> Result<Vec<u8>, Error> {
+        self.rewind()?;
+
+        let mut result = Vec::new();
+        while let Some(operation) = self.read_operation()? {
+            result.extend_from_slice(&encode_frame(&operation));
+        }
+
+        Ok(result)
+    }
+
+    /// Appends a log in the file format, as stored in backup bundles.
+    fn append_log(&mut self, log: &[u8]) -> Result<(), Error> {
+        for operation in decode_log(log)? {
+            self.append_operation(&operation)?;
+        }
+
+        self.rewind()
+    }
+}
+
+pub(super) const TEMP_EXTENSION: &str = "tmp";
+pub(super) const LOCK_EXTENSION: &str = "lock";
+
+/// Keeps a timestamped copy of the registry at `path` next to it.
+pub(super) fn archive(path: &Path) -> Result<PathBuf, Error> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis();
+    let archive_path = path.with_extension(format!("{}.bak", timestamp));
+
+    if fs::hard_link(path, &archive_path).is_err() {
+        fs::copy(path, &archive_path)?;
+    }
+
+    Ok(archive_path)
+}
+
+/// Flushes the directory holding `path`, so a registry renamed into place survives a crash.
+#[cfg(unix)]
+pub(super) fn sync_parent(path: &Path) -> Result<(), Error> {
+    let parent = path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    fs::File::open(parent)?.sync_all()?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(super) fn sync_parent(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+pub(super) fn remove_files(path: &Path) -> Result<(), Error> {
+    fs::remove_file(path)?;
+    if let Err(error) = fs::remove_file(path.with_extension(LOCK_EXTENSION)) {
+        if error.kind() != io::ErrorKind::NotFound {
+            return Err(error.into());
+        }
+    }
+
+    Ok(())
+}
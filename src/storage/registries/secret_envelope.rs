@@ -0,0 +1,106 @@
+use chacha20poly1305::{aead::{Aead, Payload}, KeyInit, XChaCha20Poly1305, XNonce};
+use ecies::{encrypt, decrypt};
+use rand::RngCore;
+use rand_core::OsRng;
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+use super::secret_padding::unpad;
+
+/// Leading byte of secrets of team registries. The secret is encrypted with a random data key
+/// which is wrapped to the registry public key and to the public key of every active member.
+pub const ENVELOPE_TAG: u8 = b'K';
+
+/// Seals `plaintext` for `recipients`. `padded` records whether it was padded before.
+///
+/// Layout: tag, padded flag, recipient count, `(public key, wrapped data key)` per recipient, nonce and ciphertext.
+/// Everything before the nonce is authenticated as associated data, so the flag and recipients can not be rewritten.
+pub fn seal(plaintext: &[u8], padded: bool, recipients: &[&[u8]]) -> Result<Vec<u8>, Error> {
+    let mut data_key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(data_key.as_mut_slice());
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut result = vec![ENVELOPE_TAG, padded as u8];
+    result.extend_from_slice(&(recipients.len() as i32).to_le_bytes());
+
+    for public_key in recipients {
+        let wrapped_key = encrypt(public_key, data_key.as_slice())
+            .map_err(|_| Error::InvalidData(String::from("Recipient public key is malformed")))?;
+
+        write_bytes(&mut result, public_key);
+        write_bytes(&mut result, &wrapped_key);
+    }
+
+    let ciphertext = XChaCha20Poly1305::new(data_key.as_slice().into())
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad: &result })
+        .map_err(|_| Error::InvalidInput(String::from("Can not encrypt secret")))?;
+
+    result.extend_from_slice(&nonce);
+    result.extend_from_slice(&ciphertext);
+
+    Ok(result)
+}
+
+/// Opens a sealed secret with the key pair of one of its recipients.
+pub fn open(sealed: &[u8], public_key: &[u8], private_key: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+    let (&ENVELOPE_TAG, reader) = sealed.split_first()? else {
+        return None;
+    };
+
+    let (&padded, reader) = reader.split_first()?;
+    let (count, mut reader) = reader.split_first_chunk::<4>()?;
+    let count = i32::from_le_bytes(*count);
+
+    let mut data_key = None;
+    for _ in 0..count {
+        let recipient = read_bytes(&mut reader)?;
+        let wrapped_key = read_bytes(&mut reader)?;
+
+        if recipient == public_key {
+            data_key = Some(Zeroizing::new(decrypt(private_key, wrapped_key).ok()?));
+        }
+    }
+
+    let data_key = data_key.filter(|data_key| data_key.len() == 32)?;
+    if reader.len() < NONCE_SIZE {
+        return None;
+    }
+
+    let associated_data = &sealed[..sealed.len() - reader.len()];
+    let (nonce, ciphertext) = reader.split_at(NONCE_SIZE);
+    let plaintext = XChaCha20Poly1305::new(data_key.as_slice().into())
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: associated_data })
+        .map(Zeroizing::new)
+        .ok()?;
+
+    if padded != 0 {
+        unpad(&plaintext)
+    }
+    else {
+        Some(plaintext)
+    }
+}
+
+const NONCE_SIZE: usize = 24;
+
+fn write_bytes(writer: &mut Vec<u8>, data: &[u8]) {
+    writer.extend_from_slice(&(data.len() as i32).to_le_bytes());
+    writer.extend_from_slice(data);
+}
+
+fn read_bytes<'a>(reader: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let (size, rest) = reader.split_first_chunk::<4>()?;
+    let size = usize::try_from(i32::from_le_bytes(*size)).ok()?;
+
+    if size > rest.len() {
+        return None;
+    }
+
+    let (data, rest) = rest.split_at(size);
+    *reader = rest;
+
+    Some(data)
+}
@@ -0,0 +1,106 @@
+use std::{fmt, str::FromStr};
+
+use zeroize::Zeroizing;
+
+use crate::errors::Error;
+
+/// Bucket scheme secrets are padded to before encryption, so ciphertext length does not reveal secret length.
+///
+/// A padded secret is its length as `u32` followed by the secret and zero fill up to the bucket size.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SecretPadding {
+    /// Secrets are encrypted as they are.
+    None,
+    /// Padded to the next power of two, at least `MIN_PADDED_SIZE` bytes.
+    #[default]
+    PowerOfTwo,
+    /// Padded to the smallest bucket that fits, beyond the largest one to a multiple of it.
+    Buckets(Vec<usize>),
+}
+
+pub const MIN_PADDED_SIZE: usize = 32;
+
+/// Leading byte of stored secrets encrypted with padding. Unpadded ciphertext starts
+/// with the ephemeral public key prefix instead, `0x02`, `0x03` or `0x04`.
+pub const PADDED_SECRET_TAG: u8 = b'P';
+
+impl SecretPadding {
+    /// Size of the padded plaintext for a secret of `length` bytes.
+    pub fn padded_size(&self, length: usize) -> usize {
+        let length = length + 4;
+
+        match self {
+            Self::None => length - 4,
+            Self::PowerOfTwo => length.next_power_of_two().max(MIN_PADDED_SIZE),
+            Self::Buckets(buckets) => {
+                let largest = buckets.iter().copied().max().unwrap_or(MIN_PADDED_SIZE);
+
+                buckets.iter()
+                    .copied()
+                    .filter(|bucket| *bucket >= length)
+                    .min()
+                    .unwrap_or_else(|| length.div_ceil(largest) * largest)
+            },
+        }
+    }
+
+    /// Pads `secret`, returning `None` for [`SecretPadding::None`].
+    pub fn pad(&self, secret: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        if *self == Self::None {
+            return None;
+        }
+
+        let size = self.padded_size(secret.len());
+
+        let mut result = Zeroizing::new(Vec::with_capacity(size));
+        result.extend_from_slice(&(secret.len() as u32).to_le_bytes());
+        result.extend_from_slice(secret);
+        result.resize(size, 0);
+
+        Some(result)
+    }
+}
+
+/// Strips the padding added by [`SecretPadding::pad`].
+pub fn unpad(padded: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+    let (length, rest) = padded.split_first_chunk::<4>()?;
+    let length = u32::from_le_bytes(*length) as usize;
+
+    rest.get(..length).map(|secret| Zeroizing::new(secret.to_vec()))
+}
+
+impl fmt::Display for SecretPadding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::PowerOfTwo => write!(f, "pow2"),
+            Self::Buckets(buckets) => {
+                let buckets: Vec<_> = buckets.iter().map(|bucket| bucket.to_string()).collect();
+                write!(f, "{}", buckets.join(","))
+            },
+        }
+    }
+}
+
+/// Parses `none`, `pow2` or a comma separated list of bucket sizes in bytes such as `64,256,1024`.
+impl FromStr for SecretPadding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "none" => Ok(Self::None),
+            "pow2" => Ok(Self::PowerOfTwo),
+            buckets => {
+                let buckets = buckets.split(',')
+                    .map(|bucket| bucket.trim().parse::<usize>().ok().filter(|bucket| *bucket > 4))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| Error::InvalidInput(format!(
+                        "Invalid secret padding {}, expected none, pow2 or bucket sizes larger than 4 bytes",
+                        s,
+                    )))?;
+
+                Ok(Self::Buckets(buckets))
+            },
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::{fs::{self, File}, io::{self, Read}, path::{Path, PathBuf}, time::Duration};
+
+use rusqlite::{Connection, ErrorCode, OpenFlags, OptionalExtension, params};
+
+use crate::{errors::Error, storage::entries::EntryOperationDto};
+
+use super::{
+    operation_codec::{encode_payload, decode_payload},
+    registry_lock::{RegistryLock, LockMode},
+    registry_storage::{RegistryStorage, archive, remove_files, sync_parent, TEMP_EXTENSION, LOCK_EXTENSION},
+};
+
+/// Registry stored in a SQLite database, one row per operation indexed by entry name.
+pub struct SqliteStorage {
+    connection: Connection,
+    path: PathBuf,
+    cursor: i64,
+    lock: Option<RegistryLock>,
+}
+
+impl SqliteStorage {
+    /// Creates an empty registry database at `path`, failing if it already exists.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let lock = RegistryLock::acquire(&path.with_extension(LOCK_EXTENSION), LockMode::Exclusive, Duration::ZERO)?;
+
+        if path.exists() {
+            return Err(Error::RegistryExists(path.display().to_string()));
+        }
+
+        Self::create_database(path, Some(lock))
+    }
+
+    /// Opens the registry database at `path`, waiting up to `lock_timeout` for the lock.
+    pub fn open(path: &Path, lock_mode: LockMode, lock_timeout: Duration) -> Result<Self, Error> {
+        if !path.is_file() {
+            return Err(Error::NoSuchRegistry(path.display().to_string()));
+        }
+
+        let lock = RegistryLock::acquire(&path.with_extension(LOCK_EXTENSION), lock_mode, lock_timeout)?;
+
+        let flags = if lock_mode == LockMode::Exclusive {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        }
+        else {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        };
+
+        let result = Self {
+            connection: Connection::open_with_flags(path, flags).map_err(sqlite_error)?,
+            path: path.to_path_buf(),
+            cursor: 0,
+            lock: Some(lock),
+        };
+
+        Ok(result)
+    }
+
+    /// Reads the header region of the database at `path` without locking it.
+    pub(super) fn peek_header(path: &Path) -> Result<Vec<u8>, Error> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(sqlite_error)?;
+
+        read_header_region(&connection)
+    }
+
+    fn create_database(path: &Path, lock: Option<RegistryLock>) -> Result<Self, Error> {
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+
+        let result = Self {
+            connection,
+            path: path.to_path_buf(),
+            cursor: 0,
+            lock,
+        };
+
+        Ok(result)
+    }
+}
+
+impl RegistryStorage for SqliteStorage {
+    fn kind(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn writable(&self) -> bool {
+        self.lock.as_ref().is_none_or(|lock| lock.mode == LockMode::Exclusive)
+    }
+
+    fn read_header(&self) -> Result<Vec<u8>, Error> {
+        read_header_region(&self.connection)
+    }
+
+    fn write_header(&mut self, header_region: &[u8]) -> Result<(), Error> {
+        self.connection
+            .execute("INSERT OR REPLACE INTO header (id, region) VALUES (1, ?1)", params![header_region])
+            .map_err(sqlite_error)?;
+
+        Ok(())
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// Row id of the next operation.
+    fn position(&mut self) -> Result<u64, Error> {
+        Ok(self.cursor as u64 + 1)
+    }
+
+    fn read_operation(&mut self) -> Result<Option<EntryOperationDto>, Error> {
+        let row = self.connection
+            .query_row(
+                "SELECT id, payload FROM operations WHERE id > ?1 ORDER BY id LIMIT 1",
+                params![self.cursor],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+
+        let Some((id, payload)) = row else {
+            return Ok(None);
+        };
+
+        let result = decode_payload(&payload).map_err(|error| Error::CorruptLog {
+            offset: id as u64,
+            reason: error.to_string(),
+        })?;
+
+        self.cursor = id;
+
+        Ok(Some(result))
+    }
+
+    fn append_operation(&mut self, operation: &EntryOperationDto) -> Result<(), Error> {
+        let (name, dst_name) = match operation {
+            EntryOperationDto::Add { name, .. } => (name, None),
+            EntryOperationDto::Set { src_name, dst_name, .. } => (src_name, dst_name.as_ref()),
+            EntryOperationDto::Del { name, .. } => (name, None),
+        };
+
+        let timestamp = i64::try_from(operation.timestamp()).unwrap_or(i64::MAX);
+
+        self.connection
+            .execute(
+                "INSERT INTO operations (name, dst_name, timestamp, payload) VALUES (?1, ?2, ?3, ?4)",
+                params![name, dst_name, timestamp, encode_payload(operation)],
+            )
+            .map_err(sqlite_error)?;
+
+        self.cursor = self.connection.last_insert_rowid();
+
+        Ok(())
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        Ok(fs::metadata(&self.path)?.len())
+    }
+
+    fn create_replacement(&self, header_region: &[u8]) -> Result<Box<dyn RegistryStorage>, Error> {
+        let path = self.path.with_extension(TEMP_EXTENSION);
+        if let Err(error) = fs::remove_file(&path) {
+            if error.kind() != io::ErrorKind::NotFound {
+                return Err(error.into());
+            }
+        }
+
+        let mut result = Self::create_database(&path, None)?;
+        result.write_header(header_region)?;
+
+        Ok(Box::new(result))
+    }
+
+    fn replace(self: Box<Self>, replacement: Box<dyn RegistryStorage>, archive_current: bool) -> Result<Option<PathBuf>, Error> {
+        let replacement_path = replacement.path()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| Error::Unsupported(String::from("Registry database can only be replaced by another database")))?;
+        drop(replacement);
+
+        let archive_path = if archive_current {
+            Some(archive(&self.path)?)
+        }
+        else {
+            None
+        };
+
+        let Self { connection, path, .. } = *self;
+        connection.close().map_err(|(_, error)| sqlite_error(error))?;
+
+        fs::rename(replacement_path, &path)?;
+        sync_parent(&path)?;
+
+        Ok(archive_path)
+    }
+
+    fn install(self: Box<Self>, path: &Path) -> Result<(), Error> {
+        let Self { connection, path: source, .. } = *self;
+        connection.close().map_err(|(_, error)| sqlite_error(error))?;
+
+        fs::rename(source, path)?;
+        sync_parent(path)?;
+
+        Ok(())
+    }
+
+    fn discard(self: Box<Self>) -> Result<(), Error> {
+        if self.lock.is_some() {
+            return Err(Error::NotPermitted(String::from("Only staged registry can be discarded")));
+        }
+
+        let Self { connection, path, .. } = *self;
+        drop(connection);
+
+        fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    fn remove(self: Box<Self>) -> Result<(), Error> {
+        let Self { connection, path, .. } = *self;
+        drop(connection);
+
+        remove_files(&path)
+    }
+}
+
+/// Whether the file at `path` starts with the SQLite database magic.
+pub(super) fn is_sqlite(path: &Path) -> bool {
+    let mut magic = [0u8; 16];
+
+    File::open(path)
+        .and_then(|mut file| file.read_exact(magic.as_mut_slice()))
+        .is_ok_and(|_| magic == *SQLITE_MAGIC)
+}
+
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS header (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        region BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS operations (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        dst_name TEXT,
+        timestamp INTEGER NOT NULL,
+        payload BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS operations_name ON operations (name);
+    CREATE INDEX IF NOT EXISTS operations_dst_name ON operations (dst_name);
+";
+
+fn read_header_region(connection: &Connection) -> Result<Vec<u8>, Error> {
+    let registry: bool = connection
+        .query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'header')", [], |row| row.get(0))
+        .map_err(sqlite_error)?;
+
+    if !registry {
+        return Err(Error::InvalidData(String::from("Database is not a registry")));
+    }
+
+    connection
+        .query_row("SELECT region FROM header WHERE id = 1", [], |row| row.get(0))
+        .optional()
+        .map_err(sqlite_error)?
+        .ok_or_else(|| Error::InvalidData(String::from("Registry database has no header")))
+}
+
+fn sqlite_error(error: rusqlite::Error) -> Error {
+    match error.sqlite_error_code() {
+        Some(ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt) => Error::InvalidData(error.to_string()),
+        Some(ErrorCode::ReadOnly) => Error::NotPermitted(error.to_string()),
+        _ => Error::Io(io::Error::other(error)),
+    }
+}
@@ -0,0 +1,98 @@
+#![allow(dead_code)]
+
+use std::{fs::File, path::PathBuf};
+
+use enigmatic::{EntryOperationDto, EntryService, ExchangeRecord, KdfParameters, MemoryStorage, RegistryRepository, RegistryStorage};
+
+pub fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+pub fn open_fixture(path: &str) -> File {
+    File::open(fixture(path)).unwrap()
+}
+
+/// Reads records as printed by `export -f json`
+pub fn expected_records(path: &str) -> Vec<ExchangeRecord> {
+    serde_json::from_reader(open_fixture(path)).unwrap()
+}
+
+/// Flattens records to comparable tuples ordered by name
+pub fn record_fields(records: &[ExchangeRecord]) -> Vec<(String, String, String)> {
+    let mut result: Vec<_> = records
+        .iter()
+        .map(|record| (record.name.clone(), record.description.clone(), record.secret.to_string()))
+        .collect();
+    result.sort();
+    result
+}
+
+/// Creates unlocked registry in memory with cheap key derivation
+pub fn memory_registry() -> EntryService {
+    EntryService::new(init_registry(&MemoryStorage::new(), false, false), true).unwrap()
+}
+
+/// Empty scratch directory unique to the test process and its name, removed on drop
+pub struct ScratchDir(PathBuf);
+
+impl std::ops::Deref for ScratchDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn scratch_dir(name: &str) -> ScratchDir {
+    let path = std::env::temp_dir().join(format!("enigmatic-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    ScratchDir(path)
+}
+
+/// Creates registry in `storage` with cheap key derivation
+pub fn init_registry(storage: &MemoryStorage, authenticated: bool, private_metadata: bool) -> RegistryRepository {
+    RegistryRepository::init_with(
+        Box::new(storage.clone()),
+        "test",
+        "password",
+        authenticated,
+        private_metadata,
+        KdfParameters::argon2id(8, 1),
+    ).unwrap()
+}
+
+/// Opens registry in `storage` unlocked with the password of `init_registry`
+pub fn open_registry(storage: &MemoryStorage) -> RegistryRepository {
+    let mut registry = RegistryRepository::with_storage(Box::new(storage.clone())).unwrap();
+    registry.unlock("password").unwrap();
+    registry
+}
+
+/// Every operation of `storage` in log order
+pub fn operations(storage: &MemoryStorage) -> Vec<EntryOperationDto> {
+    let mut storage = storage.clone();
+    storage.rewind().unwrap();
+
+    let mut result = Vec::new();
+    while let Some(operation) = storage.read_operation().unwrap() {
+        result.push(operation);
+    }
+    result
+}
+
+/// Copies header and log of `storage` to an independent replica
+pub fn replicate(storage: &MemoryStorage) -> MemoryStorage {
+    let mut result = MemoryStorage::new();
+    result.write_header(&storage.read_header().unwrap()).unwrap();
+    for operation in operations(storage) {
+        result.append_operation(&operation).unwrap();
+    }
+    result
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use enigmatic::{EntryChange, EntryRevision, EntryService};
+
+use common::memory_registry;
+
+fn secrets(entries: &EntryService, history: &[EntryRevision]) -> Vec<Vec<u8>> {
+    history.iter()
+        .map(|revision| entries.decrypt_secret(&revision.secret).unwrap().to_vec())
+        .collect()
+}
+
+#[test]
+fn history_follows_renames() {
+    let mut entries = memory_registry();
+    entries.add(String::from("db"), String::from("Production"), b"first").unwrap();
+    entries.set(String::from("db"), None, Some(String::from("Primary")), None).unwrap();
+    entries.set(String::from("db"), Some(String::from("prod")), None, None).unwrap();
+    entries.set(String::from("prod"), None, None, Some(b"second".as_slice())).unwrap();
+    entries.add(String::from("db"), String::new(), b"other").unwrap();
+
+    let history = entries.history("prod").unwrap();
+
+    assert_eq!(history.iter().map(|revision| revision.version).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    assert_eq!(history.iter().map(|revision| revision.name.as_str()).collect::<Vec<_>>(), vec!["db", "db", "prod", "prod"]);
+    assert_eq!(history.iter().map(|revision| revision.description.as_str()).collect::<Vec<_>>(), vec!["Production", "Primary", "Primary", "Primary"]);
+    assert_eq!(secrets(&entries, &history), vec![b"first".to_vec(), b"first".to_vec(), b"first".to_vec(), b"second".to_vec()]);
+
+    assert!(matches!(history[0].change, EntryChange::Added));
+    assert!(matches!(history[1].change, EntryChange::Changed { renamed_from: None, description: true, secret: false }));
+    assert!(matches!(&history[2].change, EntryChange::Changed { renamed_from: Some(name), description: false, secret: false } if name == "db"));
+    assert!(matches!(history[3].change, EntryChange::Changed { renamed_from: None, description: false, secret: true }));
+
+    let history = entries.history("db").unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(secrets(&entries, &history), vec![b"other".to_vec()]);
+}
+
+#[test]
+fn history_of_deleted_entry_ends_with_deletion() {
+    let mut entries = memory_registry();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    entries.set(String::from("db"), None, None, Some(b"second".as_slice())).unwrap();
+    entries.del(String::from("db")).unwrap();
+
+    let history = entries.history("db").unwrap();
+
+    assert_eq!(history.len(), 3);
+    assert!(matches!(history[2].change, EntryChange::Deleted));
+    assert_eq!(secrets(&entries, &history), vec![b"first".to_vec(), b"second".to_vec(), b"second".to_vec()]);
+    assert!(entries.history("unknown").unwrap().is_empty());
+}
+
+/// `show --version N` displays the revision numbered `N` by `history`.
+#[test]
+fn versions_keep_previous_secrets() {
+    let mut entries = memory_registry();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    entries.set(String::from("db"), None, None, Some(b"second".as_slice())).unwrap();
+
+    let history = entries.history("db").unwrap();
+    let version = history.iter().find(|revision| revision.version == 1).unwrap();
+
+    assert_eq!(entries.decrypt_secret(&version.secret).unwrap().as_slice(), b"first");
+    assert_eq!(entries.decrypt_secret(&entries.get("db").unwrap().secret).unwrap().as_slice(), b"second");
+    assert!(history.iter().all(|revision| revision.version != 3));
+}
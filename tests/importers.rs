@@ -0,0 +1,91 @@
+mod common;
+
+use enigmatic::{ExchangeContent, ExchangeFormat, ImportConflict};
+
+use common::{expected_records, memory_registry, open_fixture, record_fields};
+
+fn read(format: ExchangeFormat, path: &str) -> ExchangeContent {
+    format.read(open_fixture(path), None).unwrap()
+}
+
+#[test]
+fn reads_bitwarden_export() {
+    let content = read(ExchangeFormat::Bitwarden, "bitwarden/export.json");
+
+    assert_eq!(record_fields(&content.records), record_fields(&expected_records("bitwarden/expected.json")));
+    assert_eq!(content.skipped, 1);
+}
+
+#[test]
+fn reads_one_password_1pux_export() {
+    let content = read(ExchangeFormat::OnePassword, "1password/export.1pux");
+
+    assert_eq!(record_fields(&content.records), record_fields(&expected_records("1password/expected-1pux.json")));
+    assert_eq!(content.skipped, 2);
+}
+
+#[test]
+fn reads_one_password_csv_export() {
+    let content = read(ExchangeFormat::OnePassword, "1password/export.csv");
+
+    assert_eq!(record_fields(&content.records), record_fields(&expected_records("1password/expected-csv.json")));
+    assert_eq!(content.skipped, 1);
+}
+
+#[test]
+fn reads_firefox_export() {
+    let content = read(ExchangeFormat::FirefoxCsv, "firefox/logins.csv");
+
+    assert_eq!(record_fields(&content.records), record_fields(&expected_records("firefox/expected.json")));
+    assert_eq!(content.skipped, 0);
+}
+
+#[test]
+fn imports_chrome_export_renaming_duplicates() {
+    let content = read(ExchangeFormat::ChromeCsv, "chrome/passwords.csv");
+    assert_eq!(content.records.len(), 3);
+    assert_eq!(content.skipped, 0);
+
+    let mut entries = memory_registry();
+    let report = entries.import(content.records, ImportConflict::Rename, false).unwrap();
+
+    assert_eq!(report.added, vec!["github.com", "intranet.example.com"]);
+    assert_eq!(report.renamed, vec![(String::from("github.com"), String::from("github.com-1"))]);
+    assert_eq!(record_fields(&entries.export(None).unwrap()), record_fields(&expected_records("chrome/expected.json")));
+}
+
+#[test]
+fn imports_chrome_export_skipping_duplicates() {
+    let content = read(ExchangeFormat::ChromeCsv, "chrome/passwords.csv");
+
+    let mut entries = memory_registry();
+    let report = entries.import(content.records, ImportConflict::Skip, false).unwrap();
+
+    assert_eq!(report.skipped, vec!["github.com"]);
+    assert_eq!(entries.get("github.com").unwrap().description, "Username: octocat\nURL: https://github.com/session");
+    assert_eq!(entries.entries().len(), 2);
+}
+
+#[test]
+fn imports_chrome_export_overwriting_duplicates() {
+    let content = read(ExchangeFormat::ChromeCsv, "chrome/passwords.csv");
+
+    let mut entries = memory_registry();
+    let report = entries.import(content.records, ImportConflict::Overwrite, false).unwrap();
+    let entry = entries.get("github.com").unwrap();
+
+    assert_eq!(report.overwritten, vec!["github.com"]);
+    assert_eq!(entry.description, "Username: work-cat\nURL: https://github.com/session\nsecond account");
+    assert_eq!(entries.decrypt_secret(&entry.secret).unwrap().as_slice(), b"s3cr3t");
+}
+
+#[test]
+fn dry_run_import_keeps_registry() {
+    let content = read(ExchangeFormat::ChromeCsv, "chrome/passwords.csv");
+
+    let mut entries = memory_registry();
+    let report = entries.import(content.records, ImportConflict::Rename, true).unwrap();
+
+    assert_eq!(report.added.len() + report.renamed.len(), 3);
+    assert!(entries.entries().is_empty());
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use enigmatic::{EntryOperationDto, EntryService, Error, MemoryStorage, RegistryRepository, RegistryStorage};
+
+use common::{init_registry, open_registry, operations};
+
+/// Registry with three operations written with the password
+fn written_registry(authenticated: bool) -> MemoryStorage {
+    let storage = MemoryStorage::new();
+
+    let mut entries = EntryService::new(init_registry(&storage, authenticated, false), true).unwrap();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    entries.add(String::from("mail"), String::new(), b"second").unwrap();
+    entries.add(String::from("web"), String::new(), b"third").unwrap();
+
+    storage
+}
+
+/// Copy of `storage` with operation `index` changed by `tamper`
+fn tampered(storage: &MemoryStorage, index: usize, tamper: impl FnOnce(&mut EntryOperationDto)) -> MemoryStorage {
+    let mut result = MemoryStorage::new();
+    result.write_header(&storage.read_header().unwrap()).unwrap();
+
+    let mut operations = operations(storage);
+    tamper(&mut operations[index]);
+
+    for operation in &operations {
+        result.append_operation(operation).unwrap();
+    }
+
+    result
+}
+
+fn locked(storage: &MemoryStorage) -> RegistryRepository {
+    RegistryRepository::with_storage(Box::new(storage.clone())).unwrap()
+}
+
+fn assert_violation(result: Result<EntryService, Error>, index: usize) {
+    let error = result.err().unwrap();
+
+    assert!(matches!(error, Error::IntegrityViolation { operation, .. } if operation == index), "{}", error);
+    assert_eq!(error.exit_code(), 11);
+}
+
+fn change_description(operation: &mut EntryOperationDto) {
+    if let EntryOperationDto::Add { description, .. } = operation {
+        description.push_str("changed");
+    }
+}
+
+#[test]
+fn intact_chain_verifies() {
+    let storage = written_registry(false);
+
+    let entries = EntryService::new(locked(&storage), true).unwrap();
+
+    assert_eq!(entries.operations(), 3);
+}
+
+#[test]
+fn changed_operation_breaks_chain() {
+    let storage = tampered(&written_registry(false), 1, change_description);
+
+    assert_violation(EntryService::new(locked(&storage), true), 1);
+}
+
+#[test]
+fn removed_operation_breaks_chain() {
+    let source = written_registry(false);
+    let mut storage = MemoryStorage::new();
+    storage.write_header(&source.read_header().unwrap()).unwrap();
+    for operation in operations(&source).iter().skip(1) {
+        storage.append_operation(operation).unwrap();
+    }
+
+    assert_violation(EntryService::new(locked(&storage), true), 0);
+}
+
+#[test]
+fn no_verify_skips_chain_of_optional_registry() {
+    let storage = tampered(&written_registry(false), 1, change_description);
+
+    let entries = EntryService::new(open_registry(&storage), false).unwrap();
+
+    assert_eq!(entries.get("mail").unwrap().description, "changed");
+}
+
+fn forge_tag(operation: &mut EntryOperationDto) {
+    let mut hash = *operation.hash();
+    hash[40] ^= 1;
+    operation.set_hash(hash);
+}
+
+fn strip_tag(operation: &mut EntryOperationDto) {
+    let mut hash = *operation.hash();
+    hash[32..64].fill(0);
+    operation.set_hash(hash);
+}
+
+#[test]
+fn authenticated_registry_tags_every_operation() {
+    let storage = written_registry(true);
+
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+
+    assert_eq!(entries.authenticated_operations(), Some(3));
+}
+
+#[test]
+fn forged_tag_is_rejected_regardless_of_no_verify() {
+    let storage = tampered(&written_registry(true), 1, forge_tag);
+
+    assert_violation(EntryService::new(open_registry(&storage), false), 1);
+}
+
+#[test]
+fn operation_without_tag_is_rejected_by_authenticated_registry() {
+    let storage = tampered(&written_registry(true), 2, strip_tag);
+
+    assert_violation(EntryService::new(open_registry(&storage), false), 2);
+    assert_violation(EntryService::new(open_registry(&storage), true), 2);
+}
+
+#[test]
+fn locked_authenticated_registry_can_not_check_tags() {
+    let storage = tampered(&written_registry(true), 2, strip_tag);
+
+    let entries = EntryService::new(locked(&storage), false).unwrap();
+
+    assert_eq!(entries.authenticated_operations(), None);
+    assert_eq!(entries.operations(), 3);
+}
+
+#[test]
+fn authenticated_registry_refuses_writes_without_password() {
+    let storage = written_registry(true);
+
+    let mut entries = EntryService::new(locked(&storage), true).unwrap();
+    let error = entries.add(String::from("new"), String::new(), b"secret").err().unwrap();
+
+    assert!(matches!(error, Error::PasswordRequired(_)));
+    assert_eq!(operations(&storage).len(), 3);
+}
+
+#[test]
+fn optional_registry_lists_operations_written_without_password() {
+    let storage = written_registry(false);
+
+    let mut entries = EntryService::new(locked(&storage), true).unwrap();
+    entries.add(String::from("new"), String::new(), b"secret").unwrap();
+    drop(entries);
+
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+
+    assert_eq!(entries.authenticated_operations(), Some(3));
+    assert_eq!(entries.unauthenticated_operations().iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![3]);
+}
+
+#[test]
+fn optional_registry_rejects_forged_tag() {
+    let storage = tampered(&written_registry(false), 1, forge_tag);
+
+    assert_violation(EntryService::new(open_registry(&storage), true), 1);
+}
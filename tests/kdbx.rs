@@ -0,0 +1,45 @@
+mod common;
+
+use enigmatic::{Error, ExchangeFormat, ImportConflict};
+
+use common::{expected_records, memory_registry, open_fixture, record_fields};
+
+const PASSWORD: &str = "enigmatic";
+
+#[test]
+fn reads_keepass_database() {
+    let content = ExchangeFormat::Kdbx.read(open_fixture("kdbx/argon2d-aes256.kdbx"), Some(PASSWORD)).unwrap();
+
+    assert_eq!(record_fields(&content.records), record_fields(&expected_records("kdbx/expected.json")));
+    assert_eq!(content.skipped, 0);
+}
+
+#[test]
+fn reads_exported_database() {
+    let content = ExchangeFormat::Kdbx.read(open_fixture("kdbx/argon2id-chacha20.kdbx"), Some(PASSWORD)).unwrap();
+
+    assert_eq!(record_fields(&content.records), record_fields(&expected_records("kdbx/expected.json")));
+}
+
+#[test]
+fn rejects_wrong_password() {
+    let result = ExchangeFormat::Kdbx.read(open_fixture("kdbx/argon2d-aes256.kdbx"), Some("wrong"));
+
+    assert!(matches!(result, Err(Error::WrongPassword)));
+}
+
+#[test]
+fn round_trips_export() {
+    let mut source = memory_registry();
+    source.import(expected_records("kdbx/expected.json"), ImportConflict::Skip, false).unwrap();
+
+    let mut database = Vec::new();
+    ExchangeFormat::Kdbx.write(&mut database, &source.export(None).unwrap(), Some(PASSWORD)).unwrap();
+
+    let content = ExchangeFormat::Kdbx.read(database.as_slice(), Some(PASSWORD)).unwrap();
+    let mut destination = memory_registry();
+    let report = destination.import(content.records, ImportConflict::Skip, false).unwrap();
+
+    assert_eq!(report.added.len(), 4);
+    assert_eq!(record_fields(&destination.export(None).unwrap()), record_fields(&expected_records("kdbx/expected.json")));
+}
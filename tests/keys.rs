@@ -0,0 +1,98 @@
+mod common;
+
+use enigmatic::{EntryService, Error, KdfParameters, MemoryStorage, RegistryRepository};
+
+use common::{init_registry, open_registry, operations};
+
+fn written_registry() -> MemoryStorage {
+    let storage = MemoryStorage::new();
+
+    let mut entries = EntryService::new(init_registry(&storage, false, false), true).unwrap();
+    entries.add(String::from("db"), String::from("Production"), b"first").unwrap();
+    entries.set(String::from("db"), None, None, Some(b"second".as_slice())).unwrap();
+    entries.add(String::from("web"), String::new(), b"third").unwrap();
+
+    storage
+}
+
+fn unlock(storage: &MemoryStorage, password: &str) -> Result<RegistryRepository, Error> {
+    let mut registry = RegistryRepository::with_storage(Box::new(storage.clone()))?;
+    registry.unlock(password)?;
+    Ok(registry)
+}
+
+fn secret(entries: &EntryService, name: &str) -> Vec<u8> {
+    entries.decrypt_secret(&entries.get(name).unwrap().secret).unwrap().to_vec()
+}
+
+#[test]
+fn changed_password_replaces_previous_one() {
+    let storage = written_registry();
+    let hashes: Vec<_> = operations(&storage).iter().map(|operation| *operation.hash()).collect();
+
+    open_registry(&storage).change_password("changed", KdfParameters::argon2id(16, 2)).unwrap();
+
+    assert!(matches!(unlock(&storage, "password").err().unwrap(), Error::WrongPassword));
+
+    let registry = unlock(&storage, "changed").unwrap();
+    assert_eq!((registry.kdf().memory, registry.kdf().iterations), (16, 2));
+
+    let entries = EntryService::new(registry, true).unwrap();
+    assert_eq!(secret(&entries, "db"), b"second");
+    assert_eq!(secret(&entries, "web"), b"third");
+    assert_eq!(operations(&storage).iter().map(|operation| *operation.hash()).collect::<Vec<_>>(), hashes);
+}
+
+#[test]
+fn locked_registry_password_can_not_be_changed() {
+    let storage = written_registry();
+    let registry = RegistryRepository::with_storage(Box::new(storage.clone())).unwrap();
+
+    let error = registry.change_password("changed", KdfParameters::argon2id(8, 1)).err().unwrap();
+
+    assert!(matches!(error, Error::PasswordRequired(_)));
+    assert!(unlock(&storage, "password").is_ok());
+}
+
+fn history_secrets(entries: &mut EntryService, name: &str) -> Vec<Vec<u8>> {
+    entries.history(name).unwrap()
+        .iter()
+        .map(|revision| entries.decrypt_secret(&revision.secret).unwrap().to_vec())
+        .collect()
+}
+
+#[test]
+fn rotated_key_replaces_previous_one() {
+    let storage = written_registry();
+    let previous = common::replicate(&storage);
+    let public_key = open_registry(&storage).public_key().to_vec();
+
+    let report = EntryService::new(open_registry(&storage), true).unwrap().rotate_key("password", false).unwrap();
+    assert_eq!((report.entries, report.operations), (2, 2));
+
+    let registry = open_registry(&storage);
+    assert_ne!(registry.public_key(), public_key.as_slice());
+
+    let mut entries = EntryService::new(registry, true).unwrap();
+    assert_eq!(secret(&entries, "db"), b"second");
+    assert_eq!(secret(&entries, "web"), b"third");
+    assert_eq!(history_secrets(&mut entries, "db"), vec![b"second".to_vec()]);
+
+    let stale = EntryService::new(open_registry(&previous), true).unwrap();
+    assert!(stale.decrypt_secret(&entries.get("db").unwrap().secret).is_none());
+
+    let error = stale.sync(open_registry(&storage), |_| unreachable!()).err().unwrap();
+    assert!(matches!(error, Error::InvalidInput(_)), "{}", error);
+}
+
+#[test]
+fn rotated_key_keeps_history_on_request() {
+    let storage = written_registry();
+
+    let report = EntryService::new(open_registry(&storage), true).unwrap().rotate_key("password", true).unwrap();
+    assert_eq!((report.entries, report.operations), (2, 3));
+
+    let mut entries = EntryService::new(open_registry(&storage), true).unwrap();
+    assert_eq!(history_secrets(&mut entries, "db"), vec![b"first".to_vec(), b"second".to_vec()]);
+    assert_eq!(secret(&entries, "web"), b"third");
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use enigmatic::{EntryOperationDto, EntryService, Error, MemoryStorage, RegistryRepository, RegistryStorage, SyncSide};
+
+use common::{init_registry, open_registry, operations, replicate};
+
+fn private_registry() -> MemoryStorage {
+    let storage = MemoryStorage::new();
+
+    let mut entries = EntryService::new(init_registry(&storage, false, true), true).unwrap();
+    entries.add(String::from("db"), String::from("Production"), b"first").unwrap();
+    entries.set(String::from("db"), Some(String::from("db/prod")), None, None).unwrap();
+    entries.add(String::from("web"), String::from("Frontend"), b"second").unwrap();
+
+    storage
+}
+
+/// Copy of `storage` with operation `index` changed by `tamper`
+fn tampered(storage: &MemoryStorage, index: usize, tamper: impl FnOnce(&mut EntryOperationDto)) -> MemoryStorage {
+    let mut result = MemoryStorage::new();
+    result.write_header(&storage.read_header().unwrap()).unwrap();
+
+    let mut operations = operations(storage);
+    tamper(&mut operations[index]);
+
+    for operation in &operations {
+        result.append_operation(operation).unwrap();
+    }
+
+    result
+}
+
+fn assert_corrupt(storage: &MemoryStorage) {
+    let error = EntryService::new(open_registry(storage), true).err().unwrap();
+
+    assert!(matches!(error, Error::CorruptLog { .. }), "{}", error);
+}
+
+#[test]
+fn names_are_stored_as_keyed_lookups() {
+    let storage = private_registry();
+
+    for operation in operations(&storage) {
+        if let EntryOperationDto::Add { name, description, .. } = operation {
+            assert_eq!(name.len(), 64);
+            assert!(!description.contains("Production") && !description.contains("Frontend"));
+        }
+    }
+
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+    let names: Vec<_> = entries.entries().keys().map(String::as_str).collect();
+
+    assert_eq!(names, vec!["db/prod", "web"]);
+    assert_eq!(entries.get("db/prod").unwrap().description, "Production");
+}
+
+#[test]
+fn locked_registry_with_private_metadata_can_not_be_listed() {
+    let storage = private_registry();
+
+    let error = EntryService::new(RegistryRepository::with_storage(Box::new(storage.clone())).unwrap(), true).err().unwrap();
+
+    assert!(matches!(error, Error::PasswordRequired(_)));
+}
+
+#[test]
+fn changed_sealed_metadata_is_corrupt() {
+    let storage = tampered(&private_registry(), 2, |operation| {
+        if let EntryOperationDto::Add { description, .. } = operation {
+            let last = description.pop().unwrap();
+            description.push(if last == '0' { '1' } else { '0' });
+        }
+    });
+
+    assert_corrupt(&storage);
+}
+
+#[test]
+fn swapped_lookup_is_corrupt() {
+    let source = private_registry();
+    let lookup = match &operations(&source)[2] {
+        EntryOperationDto::Add { name, .. } => name.clone(),
+        _ => unreachable!(),
+    };
+
+    let storage = tampered(&source, 0, |operation| {
+        if let EntryOperationDto::Add { name, .. } = operation {
+            *name = lookup;
+        }
+    });
+
+    assert_corrupt(&storage);
+}
+
+#[test]
+fn converted_registry_syncs_with_unconverted_replica() {
+    let local = MemoryStorage::new();
+    let mut entries = EntryService::new(init_registry(&local, false, false), true).unwrap();
+    entries.add(String::from("db"), String::from("Production"), b"first").unwrap();
+    drop(entries);
+
+    let remote = replicate(&local);
+
+    open_registry(&local).convert_metadata(true).unwrap();
+    assert!(open_registry(&local).metadata_private());
+
+    let mut entries = EntryService::new(open_registry(&local), true).unwrap();
+    entries.add(String::from("web"), String::from("local"), b"local").unwrap();
+    drop(entries);
+
+    let mut entries = EntryService::new(open_registry(&remote), true).unwrap();
+    entries.add(String::from("mail"), String::from("remote"), b"remote").unwrap();
+    drop(entries);
+
+    let entries = EntryService::new(open_registry(&local), true).unwrap();
+    let report = entries.sync(
+        RegistryRepository::with_storage(Box::new(remote.clone())).unwrap(),
+        |_| Ok(SyncSide::Local),
+    ).unwrap();
+    assert_eq!((report.common, report.merged), (1, 2));
+
+    let local_entries = EntryService::new(open_registry(&local), true).unwrap();
+    let remote_entries = EntryService::new(open_registry(&remote), true).unwrap();
+
+    let hashes = |storage: &MemoryStorage| operations(storage).iter().map(|operation| *operation.hash()).collect::<Vec<_>>();
+    assert_eq!(hashes(&local), hashes(&remote));
+    assert!(!open_registry(&remote).metadata_private());
+
+    for entries in [&local_entries, &remote_entries] {
+        let names: Vec<_> = entries.entries().keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["db", "mail", "web"]);
+        assert_eq!(entries.get("mail").unwrap().description, "remote");
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use enigmatic::{EntryService, MemoryStorage, SecretPadding};
+
+use common::{init_registry, open_registry};
+
+fn padded_with(storage: &MemoryStorage, padding: SecretPadding) -> EntryService {
+    let mut registry = open_registry(storage);
+    registry.set_padding(padding);
+    EntryService::new(registry, true).unwrap()
+}
+
+fn stored(entries: &EntryService, name: &str) -> Vec<u8> {
+    entries.get(name).unwrap().secret.clone()
+}
+
+fn secret(entries: &EntryService, name: &str) -> Vec<u8> {
+    entries.decrypt_secret(&stored(entries, name)).unwrap().to_vec()
+}
+
+#[test]
+fn secrets_of_every_scheme_decrypt() {
+    let storage = MemoryStorage::new();
+    drop(init_registry(&storage, false, false));
+
+    padded_with(&storage, SecretPadding::None).add(String::from("legacy"), String::new(), b"plain").unwrap();
+    padded_with(&storage, SecretPadding::PowerOfTwo).add(String::from("pow2"), String::new(), b"padded").unwrap();
+    padded_with(&storage, SecretPadding::Buckets(vec![64, 256])).add(String::from("buckets"), String::new(), &[7; 300]).unwrap();
+
+    let entries = padded_with(&storage, SecretPadding::None);
+
+    assert!(matches!(stored(&entries, "legacy")[0], 0x02..=0x04));
+    assert_eq!(stored(&entries, "pow2")[0], b'P');
+    assert_eq!(stored(&entries, "buckets")[0], b'P');
+
+    assert_eq!(secret(&entries, "legacy"), b"plain");
+    assert_eq!(secret(&entries, "pow2"), b"padded");
+    assert_eq!(secret(&entries, "buckets"), vec![7; 300]);
+}
+
+#[test]
+fn padded_secrets_of_one_bucket_have_same_length() {
+    let storage = MemoryStorage::new();
+    let mut entries = EntryService::new(init_registry(&storage, false, false), true).unwrap();
+
+    entries.add(String::from("short"), String::new(), b"a").unwrap();
+    entries.add(String::from("long"), String::new(), &[b'a'; 27]).unwrap();
+
+    assert_eq!(stored(&entries, "short").len(), stored(&entries, "long").len());
+}
+
+#[test]
+fn pad_re_encrypts_legacy_secrets() {
+    let storage = MemoryStorage::new();
+    drop(init_registry(&storage, false, false));
+    padded_with(&storage, SecretPadding::None).add(String::from("legacy"), String::new(), b"plain").unwrap();
+
+    let report = padded_with(&storage, SecretPadding::PowerOfTwo).pad_secrets(true).unwrap();
+    assert_eq!(report.entries, 1);
+
+    let entries = padded_with(&storage, SecretPadding::None);
+    assert_eq!(stored(&entries, "legacy")[0], b'P');
+    assert_eq!(secret(&entries, "legacy"), b"plain");
+}
+
+#[test]
+fn team_envelopes_keep_padding() {
+    let storage = MemoryStorage::new();
+    let entries = EntryService::new(init_registry(&storage, false, false), true).unwrap();
+    let alice = init_registry(&MemoryStorage::new(), false, false);
+    entries.add_member("alice", alice.public_key().to_vec()).unwrap();
+
+    padded_with(&storage, SecretPadding::None).add(String::from("plain"), String::new(), b"plain").unwrap();
+    padded_with(&storage, SecretPadding::PowerOfTwo).add(String::from("padded"), String::new(), b"padded").unwrap();
+
+    let entries = padded_with(&storage, SecretPadding::None);
+
+    assert_eq!(stored(&entries, "plain")[0], b'K');
+    assert_eq!(stored(&entries, "padded")[0], b'K');
+    assert_eq!(secret(&entries, "plain"), b"plain");
+    assert_eq!(secret(&entries, "padded"), b"padded");
+}
@@ -0,0 +1,288 @@
+mod common;
+
+use std::{fs::{self, OpenOptions}, io::{Seek, SeekFrom, Write}, path::{Path, PathBuf}, time::Duration};
+
+use enigmatic::{
+    BackupBundle,
+    EntryService,
+    Error,
+    FileStorage,
+    KdfParameters,
+    LockMode,
+    MemoryStorage,
+    RegistryRepository,
+    RegistryStorage,
+    SqliteStorage,
+};
+
+use common::{scratch_dir, ScratchDir};
+
+const PASSWORD: &str = "password";
+
+/// Runs the same add/set/del, replay and compaction scenario against a backend.
+/// `open` reopens the storage created by `create` after every step.
+fn run_scenario(create: Box<dyn RegistryStorage>, open: impl Fn() -> Box<dyn RegistryStorage>) {
+    let registry = RegistryRepository::init_with(create, "test", PASSWORD, true, false, KdfParameters::argon2id(8, 1)).unwrap();
+    let mut entries = EntryService::new(registry, true).unwrap();
+
+    entries.add(String::from("db"), String::from("Production"), b"first").unwrap();
+    entries.add(String::from("mail"), String::new(), b"second").unwrap();
+    entries.add(String::from("web"), String::new(), b"third").unwrap();
+    entries.set(String::from("db"), Some(String::from("db/prod")), None, Some(b"changed".as_slice())).unwrap();
+    entries.del(String::from("mail")).unwrap();
+    drop(entries);
+
+    let entries = replay(&open);
+    assert_eq!(entries.operations(), 5);
+    assert_eq!(entries.authenticated_operations(), Some(5));
+    assert_live_entries(&entries);
+
+    entries.compact(false).unwrap();
+
+    let entries = replay(&open);
+    assert_eq!(entries.operations(), 2);
+    assert_eq!(entries.authenticated_operations(), Some(2));
+    assert_live_entries(&entries);
+}
+
+fn replay(open: impl Fn() -> Box<dyn RegistryStorage>) -> EntryService {
+    let mut registry = RegistryRepository::with_storage(open()).unwrap();
+    registry.unlock(PASSWORD).unwrap();
+
+    EntryService::new(registry, true).unwrap()
+}
+
+fn assert_live_entries(entries: &EntryService) {
+    let names: Vec<_> = entries.entries().keys().map(String::as_str).collect();
+    assert_eq!(names, vec!["db/prod", "web"]);
+
+    let entry = entries.get("db/prod").unwrap();
+    assert_eq!(entry.description, "Production");
+    assert_eq!(entries.decrypt_secret(&entry.secret).unwrap().as_slice(), b"changed");
+
+    let entry = entries.get("web").unwrap();
+    assert_eq!(entries.decrypt_secret(&entry.secret).unwrap().as_slice(), b"third");
+}
+
+fn open_timeout() -> Duration {
+    Duration::from_secs(1)
+}
+
+#[test]
+fn memory_storage() {
+    let storage = MemoryStorage::new();
+
+    run_scenario(Box::new(storage.clone()), || Box::new(storage.clone()));
+}
+
+#[test]
+fn file_storage() {
+    let directory = scratch_dir("file-storage");
+    let path = directory.join("test");
+
+    run_scenario(
+        Box::new(FileStorage::create(&path).unwrap()), 
+        || Box::new(FileStorage::open(&path, LockMode::Exclusive, open_timeout()).unwrap()),
+    );
+}
+
+#[test]
+fn sqlite_storage() {
+    let directory = scratch_dir("sqlite-storage");
+    let path = directory.join("test");
+
+    run_scenario(
+        Box::new(SqliteStorage::create(&path).unwrap()), 
+        || Box::new(SqliteStorage::open(&path, LockMode::Exclusive, open_timeout()).unwrap()),
+    );
+}
+
+/// Writes a registry file with three operations and returns its path with the offset of every frame
+fn framed_registry(name: &str) -> (ScratchDir, PathBuf, Vec<u64>) {
+    let directory = scratch_dir(name);
+    let path = directory.join("test");
+
+    let registry = RegistryRepository::init(&path, "test", PASSWORD, false, false, KdfParameters::argon2id(8, 1)).unwrap();
+    let mut entries = EntryService::new(registry, true).unwrap();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    entries.add(String::from("mail"), String::new(), b"second").unwrap();
+    entries.add(String::from("web"), String::new(), b"third").unwrap();
+    drop(entries);
+
+    let mut storage = FileStorage::open(&path, LockMode::Shared, open_timeout()).unwrap();
+    storage.rewind().unwrap();
+
+    let mut offsets = vec![storage.position().unwrap()];
+    while storage.read_operation().unwrap().is_some() {
+        offsets.push(storage.position().unwrap());
+    }
+    offsets.pop();
+
+    (directory, path, offsets)
+}
+
+fn patch(path: &Path, offset: u64, bytes: &[u8]) {
+    let mut file = OpenOptions::new().write(true).open(path).unwrap();
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.write_all(bytes).unwrap();
+}
+
+fn replay_file(path: &Path) -> Result<EntryService, Error> {
+    EntryService::new(RegistryRepository::open(path, LockMode::Exclusive, open_timeout())?, true)
+}
+
+fn assert_corrupt(path: &Path, expected: u64) {
+    let error = replay_file(path).err().unwrap();
+
+    assert!(matches!(error, Error::CorruptLog { offset, .. } if offset == expected), "{}", error);
+    assert_eq!(error.exit_code(), 10);
+}
+
+#[test]
+fn truncated_last_frame_is_torn_tail() {
+    let (_directory, path, offsets) = framed_registry("torn-tail");
+    let size = fs::metadata(&path).unwrap().len();
+    fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(size - 3).unwrap();
+
+    let entries = replay_file(&path).unwrap();
+    assert_eq!(entries.torn_tail(), Some(offsets[2]));
+    assert_eq!(entries.operations(), 2);
+    drop(entries);
+
+    let mut registry = RegistryRepository::open(&path, LockMode::Exclusive, open_timeout()).unwrap();
+    assert_eq!(registry.find_torn_tail().unwrap(), Some(offsets[2]));
+    assert_eq!(registry.truncate_tail().unwrap(), size - 3 - offsets[2]);
+    drop(registry);
+
+    let mut entries = replay_file(&path).unwrap();
+    assert_eq!(entries.torn_tail(), None);
+    entries.add(String::from("web"), String::new(), b"again").unwrap();
+    assert_eq!(entries.operations(), 3);
+}
+
+#[test]
+fn torn_tail_blocks_writes_until_repaired() {
+    let (_directory, path, offsets) = framed_registry("torn-write");
+    let size = fs::metadata(&path).unwrap().len();
+    fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(size - 3).unwrap();
+
+    let mut entries = replay_file(&path).unwrap();
+    let error = entries.add(String::from("new"), String::new(), b"secret").err().unwrap();
+
+    assert!(matches!(error, Error::CorruptLog { offset, .. } if offset == offsets[2]));
+}
+
+#[test]
+fn checksum_mismatch_inside_log_is_corrupt() {
+    let (_directory, path, offsets) = framed_registry("checksum");
+    patch(&path, offsets[1] + 12, &[0xff]);
+
+    assert_corrupt(&path, offsets[1]);
+}
+
+#[test]
+fn checksum_mismatch_of_last_frame_is_torn_tail() {
+    let (_directory, path, offsets) = framed_registry("checksum-tail");
+    let size = fs::metadata(&path).unwrap().len();
+    patch(&path, size - 1, &[0xff]);
+
+    let entries = replay_file(&path).unwrap();
+    assert_eq!(entries.torn_tail(), Some(offsets[2]));
+}
+
+#[test]
+fn negative_frame_length_is_corrupt() {
+    let (_directory, path, offsets) = framed_registry("negative-length");
+    patch(&path, offsets[1] + 4, &(-1i32).to_le_bytes());
+
+    assert_corrupt(&path, offsets[1]);
+}
+
+#[test]
+fn overrunning_frame_followed_by_records_is_corrupt() {
+    let (_directory, path, offsets) = framed_registry("overrun");
+    patch(&path, offsets[1] + 4, &i32::MAX.to_le_bytes());
+
+    assert_corrupt(&path, offsets[1]);
+}
+
+#[test]
+fn overrunning_last_frame_is_torn_tail() {
+    let (_directory, path, offsets) = framed_registry("overrun-tail");
+    patch(&path, offsets[2] + 4, &i32::MAX.to_le_bytes());
+
+    let entries = replay_file(&path).unwrap();
+    assert_eq!(entries.torn_tail(), Some(offsets[2]));
+}
+
+#[test]
+fn backup_round_trips_through_bundle() {
+    let (directory, path, _) = framed_registry("backup");
+
+    let mut entries = replay_file(&path).unwrap();
+    let bundle_path = BackupBundle::path(&directory.join("backups"), "test", 1);
+    entries.backup().unwrap().write(&bundle_path).unwrap();
+    entries.del(String::from("db")).unwrap();
+    entries.add(String::from("new"), String::new(), b"later").unwrap();
+
+    let bundle = BackupBundle::read(&bundle_path).unwrap();
+    assert_eq!((bundle.entries, bundle.operations), (3, 3));
+
+    let restored = EntryService::from_backup(&bundle, &path, PASSWORD).unwrap();
+    assert!(entries.restore(restored).unwrap().is_some());
+
+    let entries = replay_file(&path).unwrap();
+    let names: Vec<_> = entries.entries().keys().map(String::as_str).collect();
+    assert_eq!(names, vec!["db", "mail", "web"]);
+    assert_eq!(entries.operations(), 3);
+
+    let copy = directory.join("copy");
+    EntryService::from_backup(&bundle, &copy, PASSWORD).unwrap().install(&copy).unwrap();
+    assert_eq!(replay_file(&copy).unwrap().entries().len(), 3);
+}
+
+#[test]
+fn torn_tail_blocks_backup() {
+    let (_directory, path, offsets) = framed_registry("torn-backup");
+    let size = fs::metadata(&path).unwrap().len();
+    fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(size - 3).unwrap();
+
+    let error = replay_file(&path).unwrap().backup().err().unwrap();
+
+    assert!(matches!(error, Error::CorruptLog { offset, .. } if offset == offsets[2]), "{}", error);
+}
+
+#[test]
+fn torn_tail_blocks_compaction() {
+    let (_directory, path, offsets) = framed_registry("torn-compaction");
+    let size = fs::metadata(&path).unwrap().len();
+    fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(size - 3).unwrap();
+
+    let error = replay_file(&path).unwrap().compact(false).err().unwrap();
+
+    assert!(matches!(error, Error::CorruptLog { offset, .. } if offset == offsets[2]), "{}", error);
+    assert_eq!(fs::metadata(&path).unwrap().len(), size - 3);
+}
+
+#[test]
+fn exclusive_open_waits_for_other_holders() {
+    let (_directory, path, _) = framed_registry("locked");
+    let timeout = Duration::from_millis(100);
+
+    let shared = RegistryRepository::open(&path, LockMode::Shared, timeout).unwrap();
+    assert!(RegistryRepository::open(&path, LockMode::Shared, timeout).is_ok());
+
+    let error = RegistryRepository::open(&path, LockMode::Exclusive, timeout).err().unwrap();
+    assert!(matches!(error, Error::RegistryLocked), "{}", error);
+    assert_eq!(error.exit_code(), 9);
+    drop(shared);
+
+    let exclusive = RegistryRepository::open(&path, LockMode::Exclusive, timeout).unwrap();
+    for mode in [LockMode::Shared, LockMode::Exclusive] {
+        let error = RegistryRepository::open(&path, mode, timeout).err().unwrap();
+        assert!(matches!(error, Error::RegistryLocked), "{}", error);
+    }
+    drop(exclusive);
+
+    assert!(RegistryRepository::open(&path, LockMode::Exclusive, timeout).is_ok());
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use enigmatic::{EntryService, MemoryStorage, RegistryRepository, SyncSide};
+
+use common::{init_registry, open_registry, operations, replicate};
+
+fn hashes(storage: &MemoryStorage) -> Vec<[u8; 64]> {
+    operations(storage).iter().map(|operation| *operation.hash()).collect()
+}
+
+/// Replays both replicas with verification and checks they hold the same log
+fn assert_synchronized(local: &MemoryStorage, remote: &MemoryStorage) -> EntryService {
+    let local_entries = EntryService::new(open_registry(local), true).unwrap();
+    let remote_entries = EntryService::new(open_registry(remote), true).unwrap();
+
+    assert_eq!(hashes(local), hashes(remote));
+    assert_eq!(local_entries.authenticated_operations(), Some(local_entries.operations()));
+    assert_eq!(remote_entries.authenticated_operations(), Some(remote_entries.operations()));
+
+    local_entries
+}
+
+fn remote(storage: &MemoryStorage) -> RegistryRepository {
+    RegistryRepository::with_storage(Box::new(storage.clone())).unwrap()
+}
+
+#[test]
+fn fast_forwards_remote() {
+    let local = MemoryStorage::new();
+    let mut entries = EntryService::new(init_registry(&local, true, false), true).unwrap();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    drop(entries);
+
+    let replica = replicate(&local);
+
+    let mut entries = EntryService::new(open_registry(&local), true).unwrap();
+    entries.add(String::from("web"), String::new(), b"second").unwrap();
+    entries.set(String::from("db"), None, Some(String::from("Production")), None).unwrap();
+    drop(entries);
+
+    let local_hashes = hashes(&local);
+
+    let entries = EntryService::new(open_registry(&local), true).unwrap();
+    let report = entries.sync(remote(&replica), |_| panic!("fast forward has no conflicts")).unwrap();
+
+    assert_eq!((report.common, report.local, report.remote), (1, 2, 0));
+    assert_eq!((report.merged, report.conflicts, report.skipped), (2, 0, 0));
+    assert_eq!(hashes(&local), local_hashes);
+
+    let entries = assert_synchronized(&local, &replica);
+    assert_eq!(entries.get("db").unwrap().description, "Production");
+    assert!(entries.get("web").is_some());
+}
+
+#[test]
+fn merges_conflicting_changes_with_preferred_side() {
+    let local = MemoryStorage::new();
+    let mut entries = EntryService::new(init_registry(&local, true, false), true).unwrap();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    drop(entries);
+
+    let replica = replicate(&local);
+
+    let mut entries = EntryService::new(open_registry(&local), true).unwrap();
+    entries.set(String::from("db"), None, Some(String::from("local")), None).unwrap();
+    entries.add(String::from("mail"), String::new(), b"local").unwrap();
+    drop(entries);
+
+    let mut entries = EntryService::new(open_registry(&replica), true).unwrap();
+    entries.set(String::from("db"), None, Some(String::from("remote")), Some(b"remote")).unwrap();
+    entries.add(String::from("web"), String::new(), b"remote").unwrap();
+    drop(entries);
+
+    let mut conflicts = Vec::new();
+    let entries = EntryService::new(open_registry(&local), true).unwrap();
+    let report = entries.sync(remote(&replica), |conflict| {
+        conflicts.push(conflict.name.clone());
+        Ok(SyncSide::Remote)
+    }).unwrap();
+
+    assert_eq!(conflicts, vec!["db"]);
+    assert_eq!((report.common, report.local, report.remote), (1, 2, 2));
+    assert_eq!((report.merged, report.conflicts, report.skipped), (3, 1, 0));
+
+    let entries = assert_synchronized(&local, &replica);
+    let names: Vec<_> = entries.entries().keys().map(String::as_str).collect();
+    assert_eq!(names, vec!["db", "mail", "web"]);
+
+    let entry = entries.get("db").unwrap();
+    assert_eq!(entry.description, "remote");
+    assert_eq!(entries.decrypt_secret(&entry.secret).unwrap().as_slice(), b"remote");
+}
+
+#[test]
+fn rejects_unauthenticated_remote_operation_when_unlocked() {
+    let local = MemoryStorage::new();
+    let mut entries = EntryService::new(init_registry(&local, false, false), true).unwrap();
+    entries.add(String::from("db"), String::new(), b"first").unwrap();
+    drop(entries);
+
+    let replica = replicate(&local);
+
+    let mut entries = EntryService::new(remote(&replica), true).unwrap();
+    entries.add(String::from("web"), String::new(), b"unauthenticated").unwrap();
+    drop(entries);
+
+    let entries = EntryService::new(open_registry(&local), true).unwrap();
+    let error = entries.sync(remote(&replica), |_| Ok(SyncSide::Local)).err().unwrap();
+
+    assert!(matches!(error, enigmatic::Error::IntegrityViolation { operation: 1, .. }));
+    assert_eq!(error.exit_code(), 11);
+}
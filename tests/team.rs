@@ -0,0 +1,138 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use enigmatic::{EntryService, Error, MemoryStorage, RegistryRepository};
+
+use common::{init_registry, open_registry};
+
+/// Team registry with entry `db` and personal registry of member `alice`
+fn team() -> (MemoryStorage, RegistryRepository) {
+    let storage = MemoryStorage::new();
+    let mut entries = EntryService::new(init_registry(&storage, false, false), true).unwrap();
+    entries.add(String::from("db"), String::new(), b"before").unwrap();
+
+    let alice = init_registry(&MemoryStorage::new(), false, false);
+    entries.add_member("alice", alice.public_key().to_vec()).unwrap();
+
+    (storage, alice)
+}
+
+fn as_member(storage: &MemoryStorage, identity: &RegistryRepository) -> Result<RegistryRepository, Error> {
+    let mut registry = RegistryRepository::with_storage(Box::new(storage.clone()))?;
+    registry.unlock_as(identity)?;
+    Ok(registry)
+}
+
+fn secret(entries: &EntryService, name: &str) -> Option<Vec<u8>> {
+    entries.decrypt_secret(&entries.get(name).unwrap().secret).map(|secret| secret.to_vec())
+}
+
+#[test]
+fn member_reads_secrets_with_identity() {
+    let (storage, alice) = team();
+
+    let mut entries = EntryService::new(open_registry(&storage), true).unwrap();
+    entries.add(String::from("web"), String::new(), b"after").unwrap();
+
+    let entries = EntryService::new(as_member(&storage, &alice).unwrap(), true).unwrap();
+
+    assert_eq!(secret(&entries, "db").as_deref(), Some(b"before".as_slice()));
+    assert_eq!(secret(&entries, "web").as_deref(), Some(b"after".as_slice()));
+}
+
+#[test]
+fn stranger_can_not_unlock_team_registry() {
+    let (storage, _) = team();
+    let stranger = init_registry(&MemoryStorage::new(), false, false);
+
+    let error = as_member(&storage, &stranger).err().unwrap();
+
+    assert!(matches!(error, Error::NotPermitted(_)));
+}
+
+#[test]
+fn removed_member_loses_data_keys() {
+    let (storage, alice) = team();
+    let stale = as_member(&storage, &alice).unwrap();
+
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+    entries.remove_member("alice", false).unwrap();
+
+    assert!(matches!(as_member(&storage, &alice).err().unwrap(), Error::NotPermitted(_)));
+
+    let entries = EntryService::new(stale, true).unwrap();
+    assert_eq!(secret(&entries, "db"), None);
+
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+    assert_eq!(secret(&entries, "db").as_deref(), Some(b"before".as_slice()));
+}
+
+#[test]
+fn removal_with_rotation_lists_secrets_until_changed() {
+    let (storage, _) = team();
+    thread::sleep(Duration::from_millis(2));
+
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+    entries.remove_member("alice", true).unwrap();
+
+    let mut entries = EntryService::new(open_registry(&storage), true).unwrap();
+    assert_eq!(entries.rotation_pending(), vec!["db"]);
+
+    thread::sleep(Duration::from_millis(2));
+    entries.set(String::from("db"), None, None, Some(b"rotated".as_slice())).unwrap();
+    assert!(entries.rotation_pending().is_empty());
+}
+
+#[test]
+fn envelope_flag_and_recipients_are_authenticated() {
+    let (storage, alice) = team();
+    let entries = EntryService::new(open_registry(&storage), true).unwrap();
+    let sealed = entries.get("db").unwrap().secret.clone();
+
+    let mut flag_flipped = sealed.clone();
+    flag_flipped[1] ^= 1;
+    assert_eq!(entries.decrypt_secret(&flag_flipped), None);
+
+    // The second recipient key starts after tag, flag, count and the registry key with its wrapped data key.
+    let registry_key_size = u32::from_le_bytes(sealed[6..10].try_into().unwrap()) as usize;
+    let wrapped_size_offset = 10 + registry_key_size;
+    let wrapped_size = u32::from_le_bytes(sealed[wrapped_size_offset..wrapped_size_offset + 4].try_into().unwrap()) as usize;
+    let member_key_offset = wrapped_size_offset + 4 + wrapped_size + 4;
+    assert!(sealed[member_key_offset..].starts_with(alice.public_key()));
+
+    let mut recipient_changed = sealed.clone();
+    recipient_changed[member_key_offset + 1] ^= 1;
+    assert_eq!(entries.decrypt_secret(&recipient_changed), None);
+
+    assert!(entries.decrypt_secret(&sealed).is_some());
+}
+
+#[test]
+fn members_are_refused_by_authenticated_registries() {
+    let entries = EntryService::new(init_registry(&MemoryStorage::new(), true, false), true).unwrap();
+    let alice = init_registry(&MemoryStorage::new(), false, false);
+
+    let error = entries.add_member("alice", alice.public_key().to_vec()).err().unwrap();
+
+    assert!(matches!(error, Error::NotPermitted(_)));
+}
+
+#[test]
+fn members_are_refused_by_registries_with_private_metadata() {
+    let entries = EntryService::new(init_registry(&MemoryStorage::new(), false, true), true).unwrap();
+    let alice = init_registry(&MemoryStorage::new(), false, false);
+
+    let error = entries.add_member("alice", alice.public_key().to_vec()).err().unwrap();
+
+    assert!(matches!(error, Error::NotPermitted(_)));
+}
+
+#[test]
+fn team_registry_can_not_make_metadata_private() {
+    let (storage, _) = team();
+
+    let error = open_registry(&storage).convert_metadata(true).err().unwrap();
+
+    assert!(matches!(error, Error::NotPermitted(_)));
+}